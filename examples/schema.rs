@@ -3,8 +3,11 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 use token_vesting::msg::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, MasterAddressResponse, QueryMsg,
-    VestingAccountResponse,
+    AllVestingAccountsResponse, ArchivedVestingAccountsResponse, ClaimHistoryResponse,
+    ClaimOperatorsResponse, ConfigResponse, Cw20HookMsg, DelegationsResponse, ExecuteMsg,
+    InstantiateMsg, LockedBalanceResponse, MigrateMsg, PendingAdminsResponse, QueryMsg,
+    RoleMembersResponse, RolesResponse, TotalVotingPowerResponse, VestedAmountAtResponse,
+    VestingAccountResponse, VestingTimelineResponse, VotingPowerResponse,
 };
 
 fn main() {
@@ -16,7 +19,21 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &out_dir);
     export_schema(&schema_for!(ExecuteMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(MigrateMsg), &out_dir);
     export_schema(&schema_for!(Cw20HookMsg), &out_dir);
     export_schema(&schema_for!(VestingAccountResponse), &out_dir);
-    export_schema(&schema_for!(MasterAddressResponse), &out_dir);
+    export_schema(&schema_for!(AllVestingAccountsResponse), &out_dir);
+    export_schema(&schema_for!(LockedBalanceResponse), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(ClaimOperatorsResponse), &out_dir);
+    export_schema(&schema_for!(RolesResponse), &out_dir);
+    export_schema(&schema_for!(RoleMembersResponse), &out_dir);
+    export_schema(&schema_for!(PendingAdminsResponse), &out_dir);
+    export_schema(&schema_for!(VestedAmountAtResponse), &out_dir);
+    export_schema(&schema_for!(VestingTimelineResponse), &out_dir);
+    export_schema(&schema_for!(ClaimHistoryResponse), &out_dir);
+    export_schema(&schema_for!(ArchivedVestingAccountsResponse), &out_dir);
+    export_schema(&schema_for!(DelegationsResponse), &out_dir);
+    export_schema(&schema_for!(VotingPowerResponse), &out_dir);
+    export_schema(&schema_for!(TotalVotingPowerResponse), &out_dir);
 }
@@ -1,20 +1,49 @@
+use std::convert::TryInto;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    from_binary, to_binary, Attribute, BankMsg, Binary, Coin, CosmosMsg, Deps, DepsMut, Env,
-    MessageInfo, Order, Response, StdError, StdResult, Storage, Uint128, WasmMsg,
+    from_binary, to_binary, Addr, Attribute, BankMsg, Binary, BlockInfo, Coin, CosmosMsg, Decimal,
+    Deps, DepsMut, DistributionMsg, Empty, Env, Fraction, FullDelegation, MessageInfo, Order,
+    OverflowError, OverflowOperation, QuerierWrapper, Response, StakingMsg, StdError, StdResult,
+    Storage, Uint128, WasmMsg,
 };
 
 use serde_json::to_string;
 
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
-use cw_storage_plus::Bound;
+use cw2::set_contract_version;
+use cw20::{
+    BalanceResponse as Cw20BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg, Denom,
+    Expiration,
+};
+use cw_storage_plus::{Bound, PrimaryKey, U64Key};
 
+use crate::error::ContractError;
+use crate::migrations::{migrate_from_v1, migrate_from_v2, migrate_from_v3, migrate_from_v4};
 use crate::msg::{
-    Cw20HookMsg, ExecuteMsg, InstantiateMsg, MasterAddressResponse, QueryMsg,
-    VestingAccountResponse, VestingData, VestingSchedule,
+    AllVestingAccountsResponse, ArchiveReason, ArchivedVestingAccountsResponse,
+    ArchivedVestingData, ClaimHistoryResponse, ClaimKind, ClaimOperatorData,
+    ClaimOperatorsResponse, ClaimRecordData, CliffSchedule, ConfigResponse, Cw20HookMsg,
+    DelegationData, DelegationsResponse, DenomVotingPower, ExecuteMsg, InstantiateMsg,
+    LockedBalanceResponse, MigrateMsg, NewVestingAccount, PausedPeriod, PendingAdminData,
+    PendingAdminsResponse, QueryMsg, Role, RoleMembersResponse, RolesResponse, TimeUnit,
+    TotalVotingPowerResponse, Unbonding, VestedAmountAtResponse, VestingAccountData,
+    VestingAccountResponse, VestingBreakpoint, VestingData, VestingSchedule,
+    VestingTimelineResponse, VotingPowerResponse,
 };
-use crate::state::{denom_to_key, VestingAccount, MASTER_ADDRESS, VESTING_ACCOUNTS};
+use crate::state::{
+    add_voting_power, archive_vesting_account, delegate_amount, denom_to_key, has_role,
+    key_to_denom, load_config, lock_amount, next_grant_id, record_claim, slash_amount,
+    sub_voting_power, undelegate_amount, unlock_amount, vesting_accounts, write_off_amount, Config,
+    LockedBalance, PendingTransfer, StakingPosition, VestingAccount, ARCHIVED_ACCOUNTS,
+    CLAIM_HISTORY, CLAIM_OPERATORS, CLAIM_RECIPIENTS, CONFIG, CURRENT_LAYOUT_VERSION,
+    GRANT_DELEGATIONS, LAYOUT_VERSION, PENDING_ADMINS, PENDING_TRANSFERS, ROLES, TOTAL_LOCKED,
+    TOTAL_VOTING_POWER, VALIDATOR_DELEGATIONS, VOTING_POWER,
+};
+
+// version info for migration info
+const CONTRACT_NAME: &str = "crates.io:token-vesting";
+const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -22,47 +51,157 @@ pub fn instantiate(
     _env: Env,
     info: MessageInfo,
     msg: InstantiateMsg,
-) -> StdResult<Response> {
-    let master_address = if msg.master_address.is_none() {
-        info.sender.to_string()
-    } else {
-        msg.master_address.unwrap()
+) -> Result<Response, ContractError> {
+    let master_address = match msg.master_address {
+        Some(master_address) => deps.api.addr_validate(&master_address)?,
+        None => info.sender,
     };
+    let guardian = msg
+        .guardian
+        .map(|guardian| deps.api.addr_validate(&guardian))
+        .transpose()?;
 
-    MASTER_ADDRESS.save(deps.storage, &master_address)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    LAYOUT_VERSION.save(deps.storage, &CURRENT_LAYOUT_VERSION)?;
+    for role in Role::ALL {
+        ROLES.save(deps.storage, (role.key(), &master_address), &Empty {})?;
+    }
+    CONFIG.save(
+        deps.storage,
+        &Config {
+            require_transfer_approval: msg.require_transfer_approval.unwrap_or(false),
+            guardian,
+            ..Config::default()
+        },
+    )?;
     Ok(Response::new().add_attribute("master_address", master_address.as_str()))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> StdResult<Response> {
+pub fn execute(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    msg: ExecuteMsg,
+) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::Receive(msg) => receive_cw20(deps, env, info, msg),
-        ExecuteMsg::UpdateMasterAddress { master_address } => {
-            update_master_address(deps, env, info, master_address)
+        ExecuteMsg::GrantRole { role, address } => grant_role(deps, env, info, role, address),
+        ExecuteMsg::RevokeRole { role, address } => revoke_role(deps, env, info, role, address),
+        ExecuteMsg::ProposeAdmin { address, expires } => {
+            propose_admin(deps, env, info, address, expires)
+        }
+        ExecuteMsg::AcceptAdmin {} => accept_admin(deps, env, info),
+        ExecuteMsg::CancelAdminProposal { address } => {
+            cancel_admin_proposal(deps, env, info, address)
+        }
+        ExecuteMsg::UpdateConfig {
+            require_transfer_approval,
+            unbonding_period,
+        } => update_config(deps, env, info, require_transfer_approval, unbonding_period),
+        ExecuteMsg::UpdateGuardian { guardian } => update_guardian(deps, env, info, guardian),
+        ExecuteMsg::SetPaused {
+            claims,
+            registrations,
+            deregistrations,
+        } => set_paused(deps, env, info, claims, registrations, deregistrations),
+        ExecuteMsg::ApproveVestingTransfer { grant_id } => {
+            approve_vesting_transfer(deps, env, info, grant_id)
+        }
+        ExecuteMsg::SweepSurplus { denom, recipient } => {
+            sweep_surplus(deps, env, info, denom, recipient)
         }
         ExecuteMsg::RegisterVestingAccount {
             address,
             vesting_schedule,
+            time_unit,
         } => {
             // deposit validation
             if info.funds.len() != 1 {
-                return Err(StdError::generic_err("must deposit only one type of token"));
+                return Err(ContractError::InvalidFunds {});
             }
 
             let deposit_coin = info.funds[0].clone();
+            let address = deps.api.addr_validate(&address)?;
             register_vesting_account(
                 deps,
                 env,
-                info.sender.to_string(),
+                info.sender,
                 address,
                 Denom::Native(deposit_coin.denom),
                 deposit_coin.amount,
                 vesting_schedule,
+                time_unit.unwrap_or_default(),
+            )
+        }
+        ExecuteMsg::RegisterVestingAccounts { vesting_accounts } => {
+            // deposit validation
+            if info.funds.len() != 1 {
+                return Err(ContractError::InvalidFunds {});
+            }
+
+            let deposit_coin = info.funds[0].clone();
+            register_vesting_accounts(
+                deps,
+                env,
+                info.sender,
+                Denom::Native(deposit_coin.denom),
+                deposit_coin.amount,
+                vesting_accounts,
+            )
+        }
+        ExecuteMsg::TopUpVestingAccount {
+            address,
+            grant_id,
+            schedules,
+        } => {
+            // deposit validation
+            if info.funds.len() != 1 {
+                return Err(ContractError::InvalidFunds {});
+            }
+
+            let deposit_coin = info.funds[0].clone();
+            let address = deps.api.addr_validate(&address)?;
+            top_up_vesting_account(
+                deps,
+                env,
+                info.sender,
+                address,
+                grant_id,
+                Denom::Native(deposit_coin.denom),
+                deposit_coin.amount,
+                schedules,
             )
         }
+        ExecuteMsg::RevokeVestingAccount {
+            address,
+            grant_id,
+            left_vesting_token_recipient,
+        } => revoke_vesting_account(
+            deps,
+            env,
+            info,
+            address,
+            grant_id,
+            left_vesting_token_recipient,
+        ),
+        ExecuteMsg::PauseVesting { address, grant_id } => {
+            pause_vesting(deps, env, info, address, grant_id)
+        }
+        ExecuteMsg::ResumeVesting { address, grant_id } => {
+            resume_vesting(deps, env, info, address, grant_id)
+        }
+        ExecuteMsg::ForceUndelegate { address, grant_id } => {
+            force_undelegate(deps, env, info, address, grant_id)
+        }
+        ExecuteMsg::WriteOffUnbonding {
+            address,
+            grant_id,
+            amount,
+        } => write_off_unbonding(deps, env, info, address, grant_id, amount),
         ExecuteMsg::DeregisterVestingAccount {
             address,
-            denom,
+            grant_id,
             vested_token_recipient,
             left_vesting_token_recipient,
         } => deregister_vesting_account(
@@ -70,340 +209,2599 @@ pub fn execute(deps: DepsMut, env: Env, info: MessageInfo, msg: ExecuteMsg) -> S
             env,
             info,
             address,
-            denom,
+            grant_id,
             vested_token_recipient,
             left_vesting_token_recipient,
         ),
-        ExecuteMsg::Claim { denoms, recipient } => claim(deps, env, info, denoms, recipient),
+        ExecuteMsg::Claim {
+            denoms,
+            grant_ids,
+            recipient,
+        } => claim(deps, env, info, denoms, grant_ids, recipient),
+        ExecuteMsg::ClaimAll { recipient } => claim_all(deps, env, info, recipient),
+        ExecuteMsg::ClaimFor {
+            address,
+            denoms,
+            grant_ids,
+        } => claim_for(deps, env, info, address, denoms, grant_ids),
+        ExecuteMsg::ApproveClaimOperator { operator, expires } => {
+            approve_claim_operator(deps, env, info, operator, expires)
+        }
+        ExecuteMsg::RevokeClaimOperator { operator } => {
+            revoke_claim_operator(deps, env, info, operator)
+        }
+        ExecuteMsg::RegisterClaimRecipient { recipient } => {
+            register_claim_recipient(deps, env, info, recipient)
+        }
+        ExecuteMsg::TransferVestingAccount {
+            grant_id,
+            new_address,
+        } => transfer_vesting_account(deps, env, info, grant_id, new_address),
+        ExecuteMsg::Delegate {
+            grant_id,
+            validator,
+            amount,
+        } => delegate(deps, env, info, grant_id, validator, amount),
+        ExecuteMsg::Undelegate {
+            grant_id,
+            validator,
+            amount,
+        } => undelegate(deps, env, info, grant_id, validator, amount),
+        ExecuteMsg::Redelegate {
+            grant_id,
+            src_validator,
+            dst_validator,
+            amount,
+        } => redelegate(
+            deps,
+            env,
+            info,
+            grant_id,
+            src_validator,
+            dst_validator,
+            amount,
+        ),
+        ExecuteMsg::WithdrawRewards { grant_id } => withdraw_rewards(deps, env, info, grant_id),
     }
 }
 
-fn only_master(storage: &dyn Storage, sender: String) -> StdResult<()> {
-    if MASTER_ADDRESS.load(storage)? != sender {
-        return Err(StdError::generic_err("unauthorized"));
+fn only_role(storage: &dyn Storage, role: Role, sender: &Addr) -> Result<(), ContractError> {
+    if !has_role(storage, role, sender) {
+        return Err(ContractError::Unauthorized {});
     }
 
     Ok(())
 }
-fn update_master_address(
+
+fn grant_role(
     deps: DepsMut,
     _env: Env,
     info: MessageInfo,
-    master_address: String,
-) -> StdResult<Response> {
-    only_master(deps.storage, info.sender.to_string())?;
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
+
+    // an admin granted to a mistyped address would hold the contract,
+    // so the admin role is only taken over by the address accepting it
+    if role == Role::Admin {
+        return Err(ContractError::AdminRequiresAcceptance {});
+    }
 
-    MASTER_ADDRESS.save(deps.storage, &master_address)?;
+    let address = deps.api.addr_validate(&address)?;
+    ROLES.save(deps.storage, (role.key(), &address), &Empty {})?;
     Ok(Response::new().add_attributes(vec![
-        ("action", "update_master_address"),
-        ("master_address", master_address.as_str()),
+        ("action", "grant_role"),
+        ("role", role.key()),
+        ("address", address.as_str()),
     ]))
 }
 
-fn register_vesting_account(
+fn revoke_role(
     deps: DepsMut,
-    env: Env,
-    sender: String,
-    recipient: String,
-    deposit_denom: Denom,
-    deposit_amount: Uint128,
-    vesting_schedule: VestingSchedule,
-) -> StdResult<Response> {
-    only_master(deps.storage, sender)?;
-
-    let denom_key = denom_to_key(deposit_denom.clone());
+    _env: Env,
+    info: MessageInfo,
+    role: Role,
+    address: String,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
 
-    // vesting_account existence check
-    if VESTING_ACCOUNTS.has(deps.storage, (recipient.as_str(), &denom_key)) {
-        return Err(StdError::generic_err("already exists"));
+    let address = deps.api.addr_validate(&address)?;
+    if !has_role(deps.storage, role, &address) {
+        return Err(ContractError::RoleNotFound {
+            role: role.key().to_string(),
+            address: address.to_string(),
+        });
     }
 
-    // validate vesting schedule
-    vesting_schedule.validate(env.block.time.seconds(), deposit_amount)?;
-
-    VESTING_ACCOUNTS.save(
-        deps.storage,
-        (recipient.as_str(), &denom_key),
-        &VestingAccount {
-            address: recipient.to_string(),
-            vesting_denom: deposit_denom.clone(),
-            vesting_amount: deposit_amount,
-            vesting_schedule,
-            claimed_amount: Uint128::zero(),
-        },
-    )?;
+    // keep at least one admin, otherwise the roles could never be managed again
+    if role == Role::Admin
+        && ROLES
+            .prefix(Role::Admin.key())
+            .keys(deps.storage, None, None, Order::Ascending)
+            .nth(1)
+            .is_none()
+    {
+        return Err(ContractError::LastAdmin {});
+    }
 
+    ROLES.remove(deps.storage, (role.key(), &address));
     Ok(Response::new().add_attributes(vec![
-        ("action", "register_vesting_account"),
-        ("address", recipient.as_str()),
-        ("vesting_denom", &to_string(&deposit_denom).unwrap()),
-        ("vesting_amount", &deposit_amount.to_string()),
+        ("action", "revoke_role"),
+        ("role", role.key()),
+        ("address", address.as_str()),
     ]))
 }
 
-fn deregister_vesting_account(
+fn propose_admin(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
     address: String,
-    denom: Denom,
-    vested_token_recipient: Option<String>,
-    left_vesting_token_recipient: Option<String>,
-) -> StdResult<Response> {
-    only_master(deps.storage, info.sender.to_string())?;
-
-    let denom_key = denom_to_key(denom.clone());
-    let sender = info.sender;
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-
-    // vesting_account existence check
-    let account = VESTING_ACCOUNTS.may_load(deps.storage, (address.as_str(), &denom_key))?;
-    if account.is_none() {
-        return Err(StdError::generic_err(format!(
-            "vesting entry is not found for denom {:?}",
-            to_string(&denom).unwrap(),
-        )));
+    let address = deps.api.addr_validate(&address)?;
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
     }
 
-    let account = account.unwrap();
-
-    // remove vesting account
-    VESTING_ACCOUNTS.remove(deps.storage, (address.as_str(), &denom_key));
-
-    let vested_amount = account
-        .vesting_schedule
-        .vested_amount(env.block.time.seconds())?;
-    let claimed_amount = account.claimed_amount;
-
-    // transfer already vested but not claimed amount to
-    // a account address or the given `vested_token_recipient` address
-    let claimable_amount = vested_amount.checked_sub(claimed_amount)?;
-    if !claimable_amount.is_zero() {
-        let recipient = vested_token_recipient.unwrap_or_else(|| address.to_string());
-        let message: CosmosMsg = match account.vesting_denom.clone() {
-            Denom::Native(denom) => BankMsg::Send {
-                to_address: recipient,
-                amount: vec![Coin {
-                    denom,
-                    amount: claimable_amount,
-                }],
-            }
-            .into(),
-            Denom::Cw20(contract_addr) => WasmMsg::Execute {
-                contract_addr: contract_addr.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient,
-                    amount: claimable_amount,
-                })?,
-                funds: vec![],
-            }
-            .into(),
-        };
+    PENDING_ADMINS.save(deps.storage, &address, &expires)?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "propose_admin"),
+        ("address", address.as_str()),
+        ("expires", &expires.to_string()),
+    ]))
+}
 
-        messages.push(message);
+fn accept_admin(deps: DepsMut, env: Env, info: MessageInfo) -> Result<Response, ContractError> {
+    let expires = PENDING_ADMINS
+        .may_load(deps.storage, &info.sender)?
+        .ok_or_else(|| ContractError::NoPendingAdmin {
+            address: info.sender.to_string(),
+        })?;
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::AdminProposalExpired {});
     }
 
-    // transfer left vesting amount to owner or
-    // the given `left_vesting_token_recipient` address
-    let left_vesting_amount = account.vesting_amount.checked_sub(vested_amount)?;
-    if !left_vesting_amount.is_zero() {
-        let recipient = left_vesting_token_recipient.unwrap_or_else(|| sender.to_string());
-        let message: CosmosMsg = match account.vesting_denom.clone() {
-            Denom::Native(denom) => BankMsg::Send {
-                to_address: recipient,
-                amount: vec![Coin {
-                    denom,
-                    amount: left_vesting_amount,
-                }],
-            }
-            .into(),
-            Denom::Cw20(contract_addr) => WasmMsg::Execute {
-                contract_addr: contract_addr.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient,
-                    amount: left_vesting_amount,
-                })?,
-                funds: vec![],
-            }
-            .into(),
-        };
+    PENDING_ADMINS.remove(deps.storage, &info.sender);
+    ROLES.save(deps.storage, (Role::Admin.key(), &info.sender), &Empty {})?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "accept_admin"),
+        ("address", info.sender.as_str()),
+    ]))
+}
 
-        messages.push(message);
+fn cancel_admin_proposal(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    address: String,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    if !PENDING_ADMINS.has(deps.storage, &address) {
+        return Err(ContractError::NoPendingAdmin {
+            address: address.to_string(),
+        });
     }
 
-    Ok(Response::new().add_messages(messages).add_attributes(vec![
-        ("action", "deregister_vesting_account"),
+    PENDING_ADMINS.remove(deps.storage, &address);
+    Ok(Response::new().add_attributes(vec![
+        ("action", "cancel_admin_proposal"),
         ("address", address.as_str()),
-        ("vesting_denom", &to_string(&account.vesting_denom).unwrap()),
-        ("vesting_amount", &account.vesting_amount.to_string()),
-        ("vested_amount", &vested_amount.to_string()),
-        ("left_vesting_amount", &left_vesting_amount.to_string()),
     ]))
 }
 
-fn claim(
+fn update_config(
     deps: DepsMut,
-    env: Env,
+    _env: Env,
     info: MessageInfo,
-    denoms: Vec<Denom>,
-    recipient: Option<String>,
-) -> StdResult<Response> {
-    let sender = info.sender;
-    let recipient = recipient.unwrap_or_else(|| sender.to_string());
+    require_transfer_approval: Option<bool>,
+    unbonding_period: Option<u64>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
 
-    let mut messages: Vec<CosmosMsg> = vec![];
-    let mut attrs: Vec<Attribute> = vec![];
-    for denom in denoms.iter() {
-        let denom_key = denom_to_key(denom.clone());
+    let mut config = load_config(deps.storage)?;
+    if let Some(require_transfer_approval) = require_transfer_approval {
+        config.require_transfer_approval = require_transfer_approval;
+    }
+    if let Some(unbonding_period) = unbonding_period {
+        config.unbonding_period = unbonding_period;
+    }
 
-        // vesting_account existence check
-        let account = VESTING_ACCOUNTS.may_load(deps.storage, (sender.as_str(), &denom_key))?;
-        if account.is_none() {
-            return Err(StdError::generic_err(format!(
-                "vesting entry is not found for denom {}",
-                to_string(&denom).unwrap(),
-            )));
-        }
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new().add_attribute("action", "update_config"))
+}
 
-        let mut account = account.unwrap();
-        let vested_amount = account
-            .vesting_schedule
-            .vested_amount(env.block.time.seconds())?;
-        let claimed_amount = account.claimed_amount;
+fn update_guardian(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    guardian: Option<String>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
 
-        let claimable_amount = vested_amount.checked_sub(claimed_amount)?;
-        if claimable_amount.is_zero() {
-            continue;
-        }
+    let mut config = load_config(deps.storage)?;
+    config.guardian = guardian
+        .map(|guardian| deps.api.addr_validate(&guardian))
+        .transpose()?;
 
-        account.claimed_amount = vested_amount;
-        if account.claimed_amount == account.vesting_amount {
-            VESTING_ACCOUNTS.remove(deps.storage, (sender.as_str(), &denom_key));
-        } else {
-            VESTING_ACCOUNTS.save(deps.storage, (sender.as_str(), &denom_key), &account)?;
-        }
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_guardian"),
+        (
+            "guardian",
+            config
+                .guardian
+                .as_ref()
+                .map(Addr::as_str)
+                .unwrap_or_default(),
+        ),
+    ]))
+}
 
-        let message: CosmosMsg = match account.vesting_denom.clone() {
-            Denom::Native(denom) => BankMsg::Send {
-                to_address: recipient.clone(),
-                amount: vec![Coin {
-                    denom,
-                    amount: claimable_amount,
-                }],
-            }
-            .into(),
-            Denom::Cw20(contract_addr) => WasmMsg::Execute {
-                contract_addr: contract_addr.to_string(),
-                msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                    recipient: recipient.clone(),
-                    amount: claimable_amount,
-                })?,
-                funds: vec![],
-            }
-            .into(),
-        };
+fn set_paused(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    claims: Option<bool>,
+    registrations: Option<bool>,
+    deregistrations: Option<bool>,
+) -> Result<Response, ContractError> {
+    let mut config = load_config(deps.storage)?;
+    let is_admin = has_role(deps.storage, Role::Admin, &info.sender);
+    let is_guardian = config.guardian.as_ref() == Some(&info.sender);
+    if !is_admin && !is_guardian {
+        return Err(ContractError::Unauthorized {});
+    }
 
-        messages.push(message);
-        attrs.extend(
-            vec![
-                Attribute::new("vesting_denom", &to_string(&account.vesting_denom).unwrap()),
-                Attribute::new("vesting_amount", &account.vesting_amount.to_string()),
-                Attribute::new("vested_amount", &vested_amount.to_string()),
-                Attribute::new("claim_amount", &claimable_amount.to_string()),
-            ]
-            .into_iter(),
-        );
+    // the guardian cannot lift a pause
+    let unpause = [claims, registrations, deregistrations].contains(&Some(false));
+    if unpause && !is_admin {
+        return Err(ContractError::Unauthorized {});
     }
 
-    Ok(Response::new()
-        .add_messages(messages)
-        .add_attributes(vec![("action", "claim"), ("address", sender.as_str())])
-        .add_attributes(attrs))
+    let paused = &mut config.paused;
+    paused.claims = claims.unwrap_or(paused.claims);
+    paused.registrations = registrations.unwrap_or(paused.registrations);
+    paused.deregistrations = deregistrations.unwrap_or(paused.deregistrations);
+
+    CONFIG.save(deps.storage, &config)?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_paused"),
+        ("claims", &config.paused.claims.to_string()),
+        ("registrations", &config.paused.registrations.to_string()),
+        (
+            "deregistrations",
+            &config.paused.deregistrations.to_string(),
+        ),
+    ]))
 }
 
-pub fn receive_cw20(
+fn approve_vesting_transfer(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    cw20_msg: Cw20ReceiveMsg,
-) -> StdResult<Response> {
-    let amount = cw20_msg.amount;
-    let sender = cw20_msg.sender;
-    let contract = info.sender;
+    grant_id: u64,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
 
-    match from_binary(&cw20_msg.msg) {
-        Ok(Cw20HookMsg::RegisterVestingAccount {
-            address,
-            vesting_schedule,
-        }) => register_vesting_account(
-            deps,
-            env,
-            sender,
-            address,
-            Denom::Cw20(contract),
-            amount,
-            vesting_schedule,
-        ),
-        Err(_) => Err(StdError::generic_err("invalid cw20 hook message")),
-    }
+    let pending = PENDING_TRANSFERS
+        .may_load(deps.storage, U64Key::new(grant_id))?
+        .ok_or(ContractError::NoPendingTransfer { grant_id })?;
+    move_vesting_account(
+        deps.storage,
+        env.block.height,
+        &pending.address,
+        grant_id,
+        &pending.new_address,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "approve_vesting_transfer"),
+        ("address", pending.address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("new_address", pending.new_address.as_str()),
+    ]))
 }
 
-#[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::MasterAddress {} => to_binary(&master_address(deps, env)?),
-        QueryMsg::VestingAccount {
-            address,
-            start_after,
-            limit,
-        } => to_binary(&vesting_account(deps, env, address, start_after, limit)?),
+fn sweep_surplus(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denom: Denom,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
+
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender,
+    };
+
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED
+        .may_load(deps.storage, &denom_key)?
+        .unwrap_or_else(|| LockedBalance::new(denom.clone()));
+    let balance = query_balance(&deps.querier, &env.contract.address, &denom)?;
+
+    // delegated and unbonding tokens are owed while away from the balance,
+    // and the balance can be short of the rest only if funds were lost,
+    // in which case there is nothing to sweep either
+    locked.settle_unbonding(balance);
+    TOTAL_LOCKED.save(deps.storage, &denom_key, &locked)?;
+    let locked_amount = locked.locked_amount;
+    let surplus_amount = locked.surplus_amount(balance);
+    if surplus_amount.is_zero() {
+        return Err(ContractError::NoSurplus {
+            denom: to_string(&denom).unwrap(),
+        });
     }
-}
 
-fn master_address(deps: Deps, _env: Env) -> StdResult<MasterAddressResponse> {
-    let master_address = MASTER_ADDRESS.load(deps.storage)?;
-    Ok(MasterAddressResponse { master_address })
+    Ok(Response::new()
+        .add_message(transfer_msg(&denom, &recipient, surplus_amount)?)
+        .add_attributes(vec![
+            ("action", "sweep_surplus"),
+            ("vesting_denom", &to_string(&denom).unwrap()),
+            ("recipient", recipient.as_str()),
+            ("locked_amount", &locked_amount.to_string()),
+            ("surplus_amount", &surplus_amount.to_string()),
+        ]))
 }
 
-const MAX_LIMIT: u32 = 30;
-const DEFAULT_LIMIT: u32 = 10;
-fn vesting_account(
-    deps: Deps,
+#[allow(clippy::too_many_arguments)]
+fn register_vesting_account(
+    deps: DepsMut,
     env: Env,
-    address: String,
-    start_after: Option<Denom>,
-    limit: Option<u32>,
-) -> StdResult<VestingAccountResponse> {
-    let mut vestings: Vec<VestingData> = vec![];
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-
-    for item in VESTING_ACCOUNTS
-        .prefix(address.as_str())
-        .range(
-            deps.storage,
-            start_after
-                .map(denom_to_key)
-                .map(|v| v.as_bytes().to_vec())
-                .map(Bound::Exclusive),
-            None,
-            Order::Ascending,
-        )
-        .take(limit)
-    {
-        let (_, account) = item?;
-        let vested_amount = account
-            .vesting_schedule
-            .vested_amount(env.block.time.seconds())?;
-
-        vestings.push(VestingData {
-            vesting_denom: account.vesting_denom,
-            vesting_amount: account.vesting_amount,
-            vested_amount,
-            vesting_schedule: account.vesting_schedule,
-            claimable_amount: vested_amount.checked_sub(account.claimed_amount)?,
-        })
+    sender: Addr,
+    recipient: Addr,
+    deposit_denom: Denom,
+    deposit_amount: Uint128,
+    vesting_schedule: VestingSchedule,
+    time_unit: TimeUnit,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Registrar, &sender)?;
+    if load_config(deps.storage)?.paused.registrations {
+        return Err(ContractError::RegistrationsPaused {});
     }
 
-    Ok(VestingAccountResponse { address, vestings })
+    // validate vesting schedule
+    vesting_schedule.validate(time_unit.current(&env.block), deposit_amount)?;
+
+    let grant_id = save_vesting_account(
+        deps.storage,
+        env.block.height,
+        &recipient,
+        &deposit_denom,
+        deposit_amount,
+        vesting_schedule,
+        time_unit,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "register_vesting_account"),
+        ("address", recipient.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("vesting_denom", &to_string(&deposit_denom).unwrap()),
+        ("vesting_amount", &deposit_amount.to_string()),
+    ]))
+}
+
+fn register_vesting_accounts(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    deposit_denom: Denom,
+    deposit_amount: Uint128,
+    vesting_accounts: Vec<NewVestingAccount>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Registrar, &sender)?;
+    if load_config(deps.storage)?.paused.registrations {
+        return Err(ContractError::RegistrationsPaused {});
+    }
+
+    if vesting_accounts.is_empty() {
+        return Err(ContractError::EmptyVestingAccounts {});
+    }
+
+    // validate all vesting schedules before storing any of them
+    let mut total_vesting_amount = Uint128::zero();
+    let mut registrations: Vec<(Addr, Uint128, VestingSchedule, TimeUnit)> = vec![];
+    for vesting_account in vesting_accounts {
+        let address = deps.api.addr_validate(&vesting_account.address)?;
+        let time_unit = vesting_account.time_unit.unwrap_or_default();
+        let vesting_amount = vesting_account.vesting_schedule.vesting_amount()?;
+        vesting_account
+            .vesting_schedule
+            .validate(time_unit.current(&env.block), vesting_amount)?;
+
+        total_vesting_amount = total_vesting_amount.checked_add(vesting_amount)?;
+        registrations.push((
+            address,
+            vesting_amount,
+            vesting_account.vesting_schedule,
+            time_unit,
+        ));
+    }
+
+    if total_vesting_amount != deposit_amount {
+        return Err(ContractError::DepositAmountMismatch {
+            deposit_amount,
+            vesting_amount: total_vesting_amount,
+        });
+    }
+
+    let mut attrs: Vec<Attribute> = vec![];
+    for (address, vesting_amount, vesting_schedule, time_unit) in registrations {
+        let grant_id = save_vesting_account(
+            deps.storage,
+            env.block.height,
+            &address,
+            &deposit_denom,
+            vesting_amount,
+            vesting_schedule,
+            time_unit,
+        )?;
+
+        attrs.extend(vec![
+            Attribute::new("address", address.to_string()),
+            Attribute::new("grant_id", grant_id.to_string()),
+            Attribute::new("vesting_amount", vesting_amount.to_string()),
+        ]);
+    }
+
+    Ok(Response::new()
+        .add_attributes(vec![
+            ("action", "register_vesting_accounts"),
+            ("vesting_denom", &to_string(&deposit_denom).unwrap()),
+            ("deposit_amount", &deposit_amount.to_string()),
+        ])
+        .add_attributes(attrs))
+}
+
+fn save_vesting_account(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    vesting_denom: &Denom,
+    vesting_amount: Uint128,
+    vesting_schedule: VestingSchedule,
+    time_unit: TimeUnit,
+) -> StdResult<u64> {
+    let grant_id = next_grant_id(storage)?;
+    lock_amount(storage, vesting_denom, vesting_amount)?;
+    add_voting_power(storage, height, address, vesting_denom, vesting_amount)?;
+    vesting_accounts().save(
+        storage,
+        (address, U64Key::new(grant_id)),
+        &VestingAccount {
+            address: address.clone(),
+            grant_id,
+            vesting_denom: vesting_denom.clone(),
+            vesting_amount,
+            vesting_schedule,
+            claimed_amount: Uint128::zero(),
+            time_unit,
+            revoked_at: None,
+            paused_periods: vec![],
+            delegated_amount: Uint128::zero(),
+            unbondings: vec![],
+            rewards: Uint128::zero(),
+            slashed_amount: Uint128::zero(),
+        },
+    )?;
+
+    Ok(grant_id)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn top_up_vesting_account(
+    deps: DepsMut,
+    env: Env,
+    sender: Addr,
+    address: Addr,
+    grant_id: u64,
+    deposit_denom: Denom,
+    deposit_amount: Uint128,
+    schedules: Option<Vec<CliffSchedule>>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Registrar, &sender)?;
+    if load_config(deps.storage)?.paused.registrations {
+        return Err(ContractError::RegistrationsPaused {});
+    }
+
+    let key = (&address, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+
+    if account.revoked_at.is_some() {
+        return Err(ContractError::VestingRevoked { grant_id });
+    }
+
+    if account.vesting_denom != deposit_denom {
+        return Err(ContractError::VestingDenomMismatch {
+            deposit_denom: to_string(&deposit_denom).unwrap(),
+            vesting_denom: to_string(&account.vesting_denom).unwrap(),
+        });
+    }
+
+    // claimed_amount is kept, as the vested amount never decreases by the top-up;
+    // new entries are validated against the pause-shifted schedule time
+    account.vesting_schedule = account.vesting_schedule.top_up(
+        account.schedule_time(account.time_unit.current(&env.block)),
+        deposit_amount,
+        schedules,
+    )?;
+    account.vesting_amount = account.vesting_amount.checked_add(deposit_amount)?;
+
+    lock_amount(deps.storage, &deposit_denom, deposit_amount)?;
+    add_voting_power(
+        deps.storage,
+        env.block.height,
+        &address,
+        &deposit_denom,
+        deposit_amount,
+    )?;
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "top_up_vesting_account"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("vesting_denom", &to_string(&deposit_denom).unwrap()),
+        ("top_up_amount", &deposit_amount.to_string()),
+        ("vesting_amount", &account.vesting_amount.to_string()),
+    ]))
+}
+
+fn revoke_vesting_account(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    grant_id: u64,
+    left_vesting_token_recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Revoker, &info.sender)?;
+    if load_config(deps.storage)?.paused.deregistrations {
+        return Err(ContractError::DeregistrationsPaused {});
+    }
+
+    let address = deps.api.addr_validate(&address)?;
+    let recipient = match left_vesting_token_recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => info.sender,
+    };
+
+    let key = (&address, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+    if account.revoked_at.is_some() {
+        return Err(ContractError::VestingRevoked { grant_id });
+    }
+    let returned_amount = settle_unbonding(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &account.vesting_denom,
+    )?;
+    account.settle_unbondings(&env.block, returned_amount);
+    if !account.staked_amount(&env.block, returned_amount).is_zero() {
+        return Err(ContractError::VestingStaked { grant_id });
+    }
+
+    // freeze the schedule at the current vested amount,
+    // the slashed tokens are taken from the vested tokens first
+    let vested_amount = account.vested_amount(&env.block)?;
+    let unvested_amount = account.vesting_amount.checked_sub(vested_amount)?;
+    let left_vesting_amount = unvested_amount.min(account.remaining_amount()?);
+    account.slashed_amount -= unvested_amount - left_vesting_amount;
+    account.revoked_at = Some(account.time_unit.current(&env.block));
+    account.vesting_amount = vested_amount;
+
+    unlock_amount(
+        deps.storage,
+        &account.vesting_denom,
+        Uint128::zero(),
+        left_vesting_amount,
+    )?;
+    sub_voting_power(
+        deps.storage,
+        env.block.height,
+        &address,
+        &account.vesting_denom,
+        left_vesting_amount,
+    )?;
+    if account.is_completed() {
+        archive_vesting_account(
+            deps.storage,
+            &env.block,
+            account.clone(),
+            ArchiveReason::Revoked,
+        )?;
+    } else {
+        vesting_accounts().save(deps.storage, key, &account)?;
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    if !left_vesting_amount.is_zero() {
+        messages.push(transfer_msg(
+            &account.vesting_denom,
+            &recipient,
+            left_vesting_amount,
+        )?);
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "revoke_vesting_account"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("vesting_denom", &to_string(&account.vesting_denom).unwrap()),
+        ("vested_amount", &vested_amount.to_string()),
+        ("left_vesting_amount", &left_vesting_amount.to_string()),
+    ]))
+}
+
+fn pause_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    grant_id: u64,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Revoker, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    let key = (&address, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+    if account.revoked_at.is_some() {
+        return Err(ContractError::VestingRevoked { grant_id });
+    }
+    if account.is_paused() {
+        return Err(ContractError::VestingPaused { grant_id });
+    }
+
+    let paused_at = account.time_unit.current(&env.block);
+    account.paused_periods.push(PausedPeriod {
+        start: paused_at,
+        end: None,
+    });
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "pause_vesting"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("paused_at", &paused_at.to_string()),
+    ]))
+}
+
+fn resume_vesting(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    grant_id: u64,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Revoker, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    let key = (&address, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+    if !account.is_paused() {
+        return Err(ContractError::VestingNotPaused { grant_id });
+    }
+
+    let resumed_at = account.time_unit.current(&env.block);
+    if let Some(period) = account.paused_periods.last_mut() {
+        period.end = Some(resumed_at);
+    }
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "resume_vesting"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("resumed_at", &resumed_at.to_string()),
+    ]))
+}
+
+fn deregister_vesting_account(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    grant_id: u64,
+    vested_token_recipient: Option<String>,
+    left_vesting_token_recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Revoker, &info.sender)?;
+    if load_config(deps.storage)?.paused.deregistrations {
+        return Err(ContractError::DeregistrationsPaused {});
+    }
+
+    let sender = info.sender;
+    let address = deps.api.addr_validate(&address)?;
+    let vested_token_recipient = vested_token_recipient
+        .map(|recipient| deps.api.addr_validate(&recipient))
+        .transpose()?;
+    let left_vesting_token_recipient = left_vesting_token_recipient
+        .map(|recipient| deps.api.addr_validate(&recipient))
+        .transpose()?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+
+    // vesting_account existence check
+    let account = vesting_accounts().may_load(deps.storage, (&address, U64Key::new(grant_id)))?;
+    if account.is_none() {
+        return Err(ContractError::VestingNotFound { grant_id });
+    }
+
+    let mut account = account.unwrap();
+    let returned_amount = settle_unbonding(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &account.vesting_denom,
+    )?;
+    if !account.staked_amount(&env.block, returned_amount).is_zero() {
+        return Err(ContractError::VestingStaked { grant_id });
+    }
+
+    let vested_amount = account.vested_amount(&env.block)?;
+    let claimed_amount = account.claimed_amount;
+    let remaining_amount = account.remaining_amount()?;
+
+    // transfer already vested but not claimed amount and the staking rewards
+    // to a account address or the given `vested_token_recipient` address,
+    // the slashed tokens are taken from the vested tokens first
+    let recipient = vested_token_recipient.unwrap_or_else(|| address.clone());
+    let claimable_amount = vested_amount
+        .checked_sub(claimed_amount)?
+        .saturating_sub(account.slashed_amount);
+    if !account.rewards.is_zero() {
+        let message = transfer_msg(&account.vesting_denom, &recipient, account.rewards)?;
+        unlock_amount(
+            deps.storage,
+            &account.vesting_denom,
+            account.rewards,
+            Uint128::zero(),
+        )?;
+        record_claim(
+            deps.storage,
+            &env.block,
+            grant_id,
+            account.rewards,
+            &recipient,
+            ClaimKind::Rewards,
+        )?;
+        account.rewards = Uint128::zero();
+
+        messages.push(message);
+    }
+    if !claimable_amount.is_zero() {
+        let message = transfer_msg(&account.vesting_denom, &recipient, claimable_amount)?;
+        record_claim(
+            deps.storage,
+            &env.block,
+            grant_id,
+            claimable_amount,
+            &recipient,
+            ClaimKind::Vested,
+        )?;
+
+        messages.push(message);
+    }
+
+    // remove vesting account
+    account.claimed_amount = claimed_amount.checked_add(claimable_amount)?;
+    account.unbondings = vec![];
+    archive_vesting_account(
+        deps.storage,
+        &env.block,
+        account.clone(),
+        ArchiveReason::Deregistered,
+    )?;
+
+    // transfer left vesting amount to owner or
+    // the given `left_vesting_token_recipient` address
+    let left_vesting_amount = remaining_amount.checked_sub(claimable_amount)?;
+    unlock_amount(
+        deps.storage,
+        &account.vesting_denom,
+        claimable_amount,
+        left_vesting_amount,
+    )?;
+    sub_voting_power(
+        deps.storage,
+        env.block.height,
+        &address,
+        &account.vesting_denom,
+        claimable_amount.checked_add(left_vesting_amount)?,
+    )?;
+    if !left_vesting_amount.is_zero() {
+        let recipient = left_vesting_token_recipient.unwrap_or(sender);
+        let message = transfer_msg(&account.vesting_denom, &recipient, left_vesting_amount)?;
+
+        messages.push(message);
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "deregister_vesting_account"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("vesting_denom", &to_string(&account.vesting_denom).unwrap()),
+        ("vesting_amount", &account.vesting_amount.to_string()),
+        ("vested_amount", &vested_amount.to_string()),
+        ("left_vesting_amount", &left_vesting_amount.to_string()),
+    ]))
+}
+
+fn claim(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    denoms: Vec<Denom>,
+    grant_ids: Option<Vec<u64>>,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let sender = info.sender;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => claim_recipient(deps.storage, &sender)?,
+    };
+
+    let (messages, attrs) = claim_vesting_accounts(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &sender,
+        &recipient,
+        Some(denoms),
+        grant_ids,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![("action", "claim"), ("address", sender.as_str())])
+        .add_attributes(attrs))
+}
+
+fn claim_all(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let sender = info.sender;
+    let recipient = match recipient {
+        Some(recipient) => deps.api.addr_validate(&recipient)?,
+        None => claim_recipient(deps.storage, &sender)?,
+    };
+
+    let (messages, attrs) = claim_vesting_accounts(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &sender,
+        &recipient,
+        None,
+        None,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![("action", "claim_all"), ("address", sender.as_str())])
+        .add_attributes(attrs))
+}
+
+fn claim_for(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    denoms: Vec<Denom>,
+    grant_ids: Option<Vec<u64>>,
+) -> Result<Response, ContractError> {
+    let address = deps.api.addr_validate(&address)?;
+    let expires = CLAIM_OPERATORS
+        .may_load(deps.storage, (&address, &info.sender))?
+        .ok_or(ContractError::Unauthorized {})?;
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::ClaimOperatorExpired {});
+    }
+
+    // the operator never receives the claimed tokens
+    let recipient = claim_recipient(deps.storage, &address)?;
+    let (messages, attrs) = claim_vesting_accounts(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &address,
+        &recipient,
+        Some(denoms),
+        grant_ids,
+    )?;
+
+    Ok(Response::new()
+        .add_messages(messages)
+        .add_attributes(vec![
+            ("action", "claim_for"),
+            ("address", address.as_str()),
+            ("operator", info.sender.as_str()),
+        ])
+        .add_attributes(attrs))
+}
+
+/// The pre-registered recipient of the address, or the address itself
+fn claim_recipient(storage: &dyn Storage, address: &Addr) -> StdResult<Addr> {
+    Ok(CLAIM_RECIPIENTS
+        .may_load(storage, address)?
+        .unwrap_or_else(|| address.clone()))
+}
+
+/// Claim the vested amount of the grants of `address` to `recipient`,
+/// returning the transfer messages and the attributes of each grant.
+/// Every denom the address holds is claimed if `denoms` is not given.
+fn claim_vesting_accounts(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    address: &Addr,
+    recipient: &Addr,
+    denoms: Option<Vec<Denom>>,
+    grant_ids: Option<Vec<u64>>,
+) -> Result<(Vec<CosmosMsg>, Vec<Attribute>), ContractError> {
+    if load_config(storage)?.paused.claims {
+        return Err(ContractError::ClaimsPaused {});
+    }
+
+    let accounts = vesting_accounts()
+        .prefix(address)
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, account)| account))
+        .collect::<StdResult<Vec<VestingAccount>>>()?;
+
+    // each denom is claimed once, as the grants are loaded before the claims
+    let mut unique_denoms: Vec<Denom> = vec![];
+    let denoms = denoms.unwrap_or_else(|| {
+        accounts
+            .iter()
+            .map(|account| account.vesting_denom.clone())
+            .collect()
+    });
+    for denom in denoms {
+        if !unique_denoms.contains(&denom) {
+            unique_denoms.push(denom);
+        }
+    }
+    let denoms = unique_denoms;
+
+    // every given grant must be found in the given denoms
+    if let Some(grant_ids) = &grant_ids {
+        if let Some(grant_id) = grant_ids.iter().find(|grant_id| {
+            !accounts.iter().any(|account| {
+                account.grant_id == **grant_id && denoms.contains(&account.vesting_denom)
+            })
+        }) {
+            return Err(ContractError::VestingNotFound {
+                grant_id: *grant_id,
+            });
+        }
+    }
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let mut attrs: Vec<Attribute> = vec![];
+    for denom in denoms.iter() {
+        let denom_accounts: Vec<VestingAccount> = accounts
+            .iter()
+            .filter(|account| account.vesting_denom == *denom)
+            .filter(|account| match &grant_ids {
+                Some(grant_ids) => grant_ids.contains(&account.grant_id),
+                None => true,
+            })
+            .cloned()
+            .collect();
+
+        // vesting_account existence check
+        if denom_accounts.is_empty() {
+            return Err(ContractError::DenomNotFound {
+                denom: to_string(&denom).unwrap(),
+            });
+        }
+
+        // the balance is checked before any claim of the denom is unlocked
+        let returned_amount = settle_unbonding(storage, querier, &env.contract.address, denom)?;
+        for mut account in denom_accounts {
+            let vested_amount = account.vested_amount(&env.block)?;
+            let claimed_amount = account.claimed_amount;
+
+            // staked tokens are claimable after they are back to the contract
+            account.settle_unbondings(&env.block, returned_amount);
+            let claimable_amount = vested_amount
+                .checked_sub(claimed_amount)?
+                .min(account.liquid_amount(&env.block, returned_amount)?);
+            if claimable_amount.is_zero() {
+                continue;
+            }
+
+            let key = (address, U64Key::new(account.grant_id));
+            unlock_amount(
+                storage,
+                &account.vesting_denom,
+                claimable_amount,
+                Uint128::zero(),
+            )?;
+            sub_voting_power(
+                storage,
+                env.block.height,
+                address,
+                &account.vesting_denom,
+                claimable_amount,
+            )?;
+            record_claim(
+                storage,
+                &env.block,
+                account.grant_id,
+                claimable_amount,
+                recipient,
+                ClaimKind::Vested,
+            )?;
+            account.claimed_amount += claimable_amount;
+            if account.is_completed() {
+                archive_vesting_account(
+                    storage,
+                    &env.block,
+                    account.clone(),
+                    ArchiveReason::Completed,
+                )?;
+            } else {
+                vesting_accounts().save(storage, key, &account)?;
+            }
+
+            let message = transfer_msg(&account.vesting_denom, recipient, claimable_amount)?;
+
+            messages.push(message);
+            attrs.extend(vec![
+                Attribute::new("grant_id", account.grant_id.to_string()),
+                Attribute::new("vesting_denom", to_string(&account.vesting_denom).unwrap()),
+                Attribute::new("vesting_amount", account.vesting_amount.to_string()),
+                Attribute::new("vested_amount", vested_amount.to_string()),
+                Attribute::new("claim_amount", claimable_amount.to_string()),
+            ]);
+        }
+    }
+
+    Ok((messages, attrs))
+}
+
+fn transfer_msg(denom: &Denom, recipient: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    Ok(match denom {
+        Denom::Native(denom) => BankMsg::Send {
+            to_address: recipient.to_string(),
+            amount: vec![Coin {
+                denom: denom.clone(),
+                amount,
+            }],
+        }
+        .into(),
+        Denom::Cw20(contract_addr) => WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: recipient.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    })
+}
+
+fn delegate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    grant_id: u64,
+    validator: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+    let bonded_denom = staking_denom(&deps.querier, &account)?;
+
+    // only the unvested tokens which are not staked or slashed yet can be delegated
+    let returned_amount = TOTAL_LOCKED
+        .load(deps.storage, &denom_to_key(account.vesting_denom.clone()))?
+        .total_returned();
+    let unvested_amount = account
+        .vesting_amount
+        .checked_sub(account.vested_amount(&env.block)?)?;
+    let available = unvested_amount.saturating_sub(
+        account.staked_amount(&env.block, returned_amount) + account.slashed_amount,
+    );
+    if amount.is_zero() || amount > available {
+        return Err(ContractError::ExceedsUnvestedAmount { amount, available });
+    }
+
+    let (mut validator_position, mut grant_position, mut messages) = settle_rewards(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &bonded_denom,
+        &mut account,
+        &validator,
+    )?;
+    if validator_position.slash_factor.is_zero() {
+        return Err(ContractError::ValidatorSlashed { validator });
+    }
+    validator_position.amount += amount;
+    grant_position.amount += amount;
+    account.delegated_amount += amount;
+    delegate_amount(deps.storage, &account.vesting_denom, amount)?;
+    save_positions(
+        deps.storage,
+        grant_id,
+        &validator,
+        &validator_position,
+        &grant_position,
+    )?;
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    messages.push(
+        StakingMsg::Delegate {
+            validator: validator.clone(),
+            amount: Coin {
+                denom: bonded_denom,
+                amount,
+            },
+        }
+        .into(),
+    );
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "delegate"),
+        ("address", info.sender.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("validator", &validator),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+fn undelegate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    grant_id: u64,
+    validator: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+
+    let unbonding_period = load_config(deps.storage)?.unbonding_period;
+    let messages = undelegate_grant(
+        deps.storage,
+        &deps.querier,
+        &env,
+        unbonding_period,
+        &mut account,
+        &validator,
+        Some(amount),
+    )?;
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "undelegate"),
+        ("address", info.sender.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("validator", &validator),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+fn force_undelegate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    grant_id: u64,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Revoker, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    let key = (&address, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+
+    let unbonding_period = load_config(deps.storage)?.unbonding_period;
+    let validators = GRANT_DELEGATIONS
+        .prefix(U64Key::new(grant_id))
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|validator| String::from_utf8(validator).map_err(StdError::from))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for validator in validators {
+        messages.extend(undelegate_grant(
+            deps.storage,
+            &deps.querier,
+            &env,
+            unbonding_period,
+            &mut account,
+            &validator,
+            None,
+        )?);
+    }
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "force_undelegate"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+    ]))
+}
+
+fn write_off_unbonding(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    address: String,
+    grant_id: u64,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    only_role(deps.storage, Role::Admin, &info.sender)?;
+
+    let address = deps.api.addr_validate(&address)?;
+    let key = (&address, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+
+    let returned_amount = settle_unbonding(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &account.vesting_denom,
+    )?;
+    account.settle_unbondings(&env.block, returned_amount);
+
+    // the slashing of an unbonding is only seen as a shortfall once it is overdue,
+    // so the oldest overdue unbondings are written off first
+    let now = env.block.time.seconds();
+    let overdue: Uint128 = account
+        .unbondings
+        .iter()
+        .filter(|unbonding| unbonding.completion_time <= now)
+        .map(|unbonding| unbonding.amount)
+        .sum();
+    if amount.is_zero() || amount > overdue {
+        return Err(ContractError::ExceedsOverdueUnbonding {
+            grant_id,
+            amount,
+            overdue,
+        });
+    }
+
+    let mut left_amount = amount;
+    for unbonding in account
+        .unbondings
+        .iter_mut()
+        .filter(|unbonding| unbonding.completion_time <= now)
+    {
+        let written_off = left_amount.min(unbonding.amount);
+        unbonding.amount -= written_off;
+        left_amount -= written_off;
+    }
+    account.slashed_amount += amount;
+    write_off_amount(deps.storage, &account.vesting_denom, amount)?;
+    sub_voting_power(
+        deps.storage,
+        env.block.height,
+        &account.address,
+        &account.vesting_denom,
+        amount,
+    )?;
+
+    let returned_amount = settle_unbonding(
+        deps.storage,
+        &deps.querier,
+        &env.contract.address,
+        &account.vesting_denom,
+    )?;
+    account.settle_unbondings(&env.block, returned_amount);
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "write_off_unbonding"),
+        ("address", address.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+fn redelegate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    grant_id: u64,
+    src_validator: String,
+    dst_validator: String,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, U64Key::new(grant_id));
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, key.clone())?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+    let bonded_denom = staking_denom(&deps.querier, &account)?;
+
+    let (mut src_validator_position, mut src_grant_position, mut messages) = settle_rewards(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &bonded_denom,
+        &mut account,
+        &src_validator,
+    )?;
+    if amount.is_zero() || amount > src_grant_position.amount {
+        return Err(ContractError::ExceedsDelegation {
+            validator: src_validator,
+            amount,
+            delegated: src_grant_position.amount,
+        });
+    }
+    src_validator_position.amount = src_validator_position.amount.checked_sub(amount)?;
+    src_grant_position.amount = src_grant_position.amount.checked_sub(amount)?;
+    save_positions(
+        deps.storage,
+        grant_id,
+        &src_validator,
+        &src_validator_position,
+        &src_grant_position,
+    )?;
+
+    let (mut dst_validator_position, mut dst_grant_position, dst_messages) = settle_rewards(
+        deps.storage,
+        &deps.querier,
+        &env,
+        &bonded_denom,
+        &mut account,
+        &dst_validator,
+    )?;
+    if dst_validator_position.slash_factor.is_zero() {
+        return Err(ContractError::ValidatorSlashed {
+            validator: dst_validator,
+        });
+    }
+    dst_validator_position.amount += amount;
+    dst_grant_position.amount += amount;
+    save_positions(
+        deps.storage,
+        grant_id,
+        &dst_validator,
+        &dst_validator_position,
+        &dst_grant_position,
+    )?;
+    vesting_accounts().save(deps.storage, key, &account)?;
+
+    messages.extend(dst_messages);
+    messages.push(
+        StakingMsg::Redelegate {
+            src_validator: src_validator.clone(),
+            dst_validator: dst_validator.clone(),
+            amount: Coin {
+                denom: bonded_denom,
+                amount,
+            },
+        }
+        .into(),
+    );
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "redelegate"),
+        ("address", info.sender.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("src_validator", &src_validator),
+        ("dst_validator", &dst_validator),
+        ("amount", &amount.to_string()),
+    ]))
+}
+
+fn withdraw_rewards(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    grant_id: u64,
+) -> Result<Response, ContractError> {
+    if load_config(deps.storage)?.paused.claims {
+        return Err(ContractError::ClaimsPaused {});
+    }
+
+    let sender = info.sender;
+    let mut account = vesting_accounts()
+        .may_load(deps.storage, (&sender, U64Key::new(grant_id)))?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+    let bonded_denom = staking_denom(&deps.querier, &account)?;
+
+    let validators = GRANT_DELEGATIONS
+        .prefix(U64Key::new(grant_id))
+        .keys(deps.storage, None, None, Order::Ascending)
+        .map(|validator| String::from_utf8(validator).map_err(StdError::from))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    for validator in validators {
+        let (validator_position, grant_position, withdraw_messages) = settle_rewards(
+            deps.storage,
+            &deps.querier,
+            &env,
+            &bonded_denom,
+            &mut account,
+            &validator,
+        )?;
+        save_positions(
+            deps.storage,
+            grant_id,
+            &validator,
+            &validator_position,
+            &grant_position,
+        )?;
+        messages.extend(withdraw_messages);
+    }
+
+    // the rewards are sent after they are withdrawn to the contract
+    let rewards = account.rewards;
+    if !rewards.is_zero() {
+        let recipient = claim_recipient(deps.storage, &sender)?;
+        messages.push(transfer_msg(&account.vesting_denom, &recipient, rewards)?);
+        unlock_amount(
+            deps.storage,
+            &account.vesting_denom,
+            rewards,
+            Uint128::zero(),
+        )?;
+        record_claim(
+            deps.storage,
+            &env.block,
+            grant_id,
+            rewards,
+            &recipient,
+            ClaimKind::Rewards,
+        )?;
+        account.rewards = Uint128::zero();
+    }
+
+    if account.is_completed() {
+        archive_vesting_account(deps.storage, &env.block, account, ArchiveReason::Completed)?;
+    } else {
+        vesting_accounts().save(deps.storage, (&sender, U64Key::new(grant_id)), &account)?;
+    }
+
+    Ok(Response::new().add_messages(messages).add_attributes(vec![
+        ("action", "withdraw_rewards"),
+        ("address", sender.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("rewards", &rewards.to_string()),
+    ]))
+}
+
+/// The bonded denom, which must be the native denom of the grant
+fn staking_denom(
+    querier: &QuerierWrapper,
+    account: &VestingAccount,
+) -> Result<String, ContractError> {
+    let bonded_denom = querier.query_bonded_denom()?;
+    match &account.vesting_denom {
+        Denom::Native(denom) if *denom == bonded_denom => Ok(bonded_denom),
+        denom => Err(ContractError::InvalidStakingDenom {
+            denom: to_string(denom).unwrap(),
+        }),
+    }
+}
+
+fn undelegate_grant(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    unbonding_period: u64,
+    account: &mut VestingAccount,
+    validator: &str,
+    amount: Option<Uint128>,
+) -> Result<Vec<CosmosMsg>, ContractError> {
+    let completion_time = env
+        .block
+        .time
+        .seconds()
+        .checked_add(unbonding_period)
+        .ok_or_else(|| {
+            OverflowError::new(
+                OverflowOperation::Add,
+                env.block.time.seconds(),
+                unbonding_period,
+            )
+        })?;
+    let bonded_denom = staking_denom(querier, account)?;
+    let (mut validator_position, mut grant_position, mut messages) =
+        settle_rewards(storage, querier, env, &bonded_denom, account, validator)?;
+
+    // nothing is left to undelegate once the validator slashed everything
+    let amount = match amount {
+        Some(amount) => amount,
+        None if grant_position.amount.is_zero() => {
+            save_positions(
+                storage,
+                account.grant_id,
+                validator,
+                &validator_position,
+                &grant_position,
+            )?;
+            return Ok(messages);
+        }
+        None => grant_position.amount,
+    };
+    if amount.is_zero() || amount > grant_position.amount {
+        return Err(ContractError::ExceedsDelegation {
+            validator: validator.to_string(),
+            amount,
+            delegated: grant_position.amount,
+        });
+    }
+
+    validator_position.amount = validator_position.amount.checked_sub(amount)?;
+    grant_position.amount = grant_position.amount.checked_sub(amount)?;
+    account.delegated_amount = account.delegated_amount.checked_sub(amount)?;
+    let position = undelegate_amount(storage, &account.vesting_denom, amount)?;
+    save_positions(
+        storage,
+        account.grant_id,
+        validator,
+        &validator_position,
+        &grant_position,
+    )?;
+
+    account.unbondings.push(Unbonding {
+        amount,
+        completion_time,
+        position,
+    });
+
+    messages.push(
+        StakingMsg::Undelegate {
+            validator: validator.to_string(),
+            amount: Coin {
+                denom: bonded_denom,
+                amount,
+            },
+        }
+        .into(),
+    );
+    Ok(messages)
+}
+
+/// Account the slashing and the rewards on the validator since the last settlement to the grant.
+/// It returns the positions of the validator and the grant to be saved,
+/// with the message withdrawing the accrued rewards to the contract.
+fn settle_rewards(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    env: &Env,
+    bonded_denom: &str,
+    account: &mut VestingAccount,
+    validator: &str,
+) -> Result<(StakingPosition, StakingPosition, Vec<CosmosMsg>), ContractError> {
+    let mut validator_position = VALIDATOR_DELEGATIONS
+        .may_load(storage, validator)?
+        .unwrap_or_default();
+
+    let mut messages: Vec<CosmosMsg> = vec![];
+    let delegation = querier.query_delegation(&env.contract.address, validator)?;
+    let rewards = reconcile_validator(&mut validator_position, delegation.as_ref(), bonded_denom);
+    if !rewards.is_zero() && !validator_position.amount.is_zero() {
+        lock_amount(storage, &Denom::Native(bonded_denom.to_string()), rewards)?;
+    }
+    if delegation.is_some() {
+        messages.push(
+            DistributionMsg::WithdrawDelegatorReward {
+                validator: validator.to_string(),
+            }
+            .into(),
+        );
+    }
+
+    // the slashed tokens are no longer owed to the grant
+    let mut grant_position = GRANT_DELEGATIONS
+        .may_load(storage, (U64Key::new(account.grant_id), validator))?
+        .unwrap_or_default();
+    let unslashed_amount = grant_position.unslashed_amount(&validator_position);
+    let slashed_amount = grant_position.amount.checked_sub(unslashed_amount)?;
+    if !slashed_amount.is_zero() {
+        account.delegated_amount = account.delegated_amount.checked_sub(slashed_amount)?;
+        account.slashed_amount += slashed_amount;
+        slash_amount(storage, &account.vesting_denom, slashed_amount)?;
+        sub_voting_power(
+            storage,
+            env.block.height,
+            &account.address,
+            &account.vesting_denom,
+            slashed_amount,
+        )?;
+    }
+    grant_position.amount = unslashed_amount;
+    grant_position.slash_factor = validator_position.slash_factor;
+
+    account.rewards +=
+        grant_position.amount * (validator_position.reward_index - grant_position.reward_index);
+    grant_position.reward_index = validator_position.reward_index;
+
+    Ok((validator_position, grant_position, messages))
+}
+
+/// Reconcile the recorded delegation of the validator with the actual delegation,
+/// returning the rewards in the bonded denom accrued since the last settlement.
+/// Any shortfall of the actual delegation is slashing, which scales down every grant position.
+fn reconcile_validator(
+    validator_position: &mut StakingPosition,
+    delegation: Option<&FullDelegation>,
+    bonded_denom: &str,
+) -> Uint128 {
+    let delegated_amount = delegation
+        .map(|delegation| delegation.amount.amount)
+        .unwrap_or_default();
+    if delegated_amount < validator_position.amount {
+        let slash_factor = validator_position.slash_factor;
+        validator_position.slash_factor = Decimal::from_ratio(
+            Uint128::new(slash_factor.numerator())
+                .multiply_ratio(delegated_amount, validator_position.amount),
+            slash_factor.denominator(),
+        );
+        validator_position.amount = delegated_amount;
+    }
+
+    let rewards = delegation
+        .map(|delegation| accumulated_rewards(delegation, bonded_denom))
+        .unwrap_or_default();
+    if !rewards.is_zero() && !validator_position.amount.is_zero() {
+        validator_position.reward_index = validator_position.reward_index
+            + Decimal::from_ratio(rewards, validator_position.amount);
+    }
+    rewards
+}
+
+fn save_positions(
+    storage: &mut dyn Storage,
+    grant_id: u64,
+    validator: &str,
+    validator_position: &StakingPosition,
+    grant_position: &StakingPosition,
+) -> StdResult<()> {
+    VALIDATOR_DELEGATIONS.save(storage, validator, validator_position)?;
+    let key = (U64Key::new(grant_id), validator);
+    if grant_position.amount.is_zero() {
+        GRANT_DELEGATIONS.remove(storage, key);
+        Ok(())
+    } else {
+        GRANT_DELEGATIONS.save(storage, key, grant_position)
+    }
+}
+
+/// Rewards in the bonded denom, the other denoms are left as surplus
+fn accumulated_rewards(delegation: &FullDelegation, bonded_denom: &str) -> Uint128 {
+    delegation
+        .accumulated_rewards
+        .iter()
+        .filter(|coin| coin.denom == bonded_denom)
+        .map(|coin| coin.amount)
+        .sum()
+}
+
+fn approve_claim_operator(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    operator: String,
+    expires: Option<Expiration>,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    let expires = expires.unwrap_or_default();
+    if expires.is_expired(&env.block) {
+        return Err(ContractError::InvalidExpiration {});
+    }
+
+    CLAIM_OPERATORS.save(deps.storage, (&info.sender, &operator), &expires)?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "approve_claim_operator"),
+        ("address", info.sender.as_str()),
+        ("operator", operator.as_str()),
+        ("expires", &expires.to_string()),
+    ]))
+}
+
+fn revoke_claim_operator(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    operator: String,
+) -> Result<Response, ContractError> {
+    let operator = deps.api.addr_validate(&operator)?;
+    CLAIM_OPERATORS.remove(deps.storage, (&info.sender, &operator));
+    Ok(Response::new().add_attributes(vec![
+        ("action", "revoke_claim_operator"),
+        ("address", info.sender.as_str()),
+        ("operator", operator.as_str()),
+    ]))
+}
+
+fn register_claim_recipient(
+    deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let recipient = match recipient {
+        Some(recipient) => {
+            let recipient = deps.api.addr_validate(&recipient)?;
+            CLAIM_RECIPIENTS.save(deps.storage, &info.sender, &recipient)?;
+            recipient
+        }
+        None => {
+            CLAIM_RECIPIENTS.remove(deps.storage, &info.sender);
+            info.sender.clone()
+        }
+    };
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "register_claim_recipient"),
+        ("address", info.sender.as_str()),
+        ("recipient", recipient.as_str()),
+    ]))
+}
+
+fn transfer_vesting_account(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    grant_id: u64,
+    new_address: String,
+) -> Result<Response, ContractError> {
+    let sender = info.sender;
+    let new_address = deps.api.addr_validate(&new_address)?;
+
+    // only the beneficiary can transfer its own grant
+    let key = (&sender, U64Key::new(grant_id));
+    if vesting_accounts().may_load(deps.storage, key)?.is_none() {
+        return Err(ContractError::VestingNotFound { grant_id });
+    }
+
+    let status = if load_config(deps.storage)?.require_transfer_approval {
+        PENDING_TRANSFERS.save(
+            deps.storage,
+            U64Key::new(grant_id),
+            &PendingTransfer {
+                address: sender.clone(),
+                new_address: new_address.clone(),
+            },
+        )?;
+        "pending"
+    } else {
+        move_vesting_account(
+            deps.storage,
+            env.block.height,
+            &sender,
+            grant_id,
+            &new_address,
+        )?;
+        "transferred"
+    };
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "transfer_vesting_account"),
+        ("address", sender.as_str()),
+        ("grant_id", &grant_id.to_string()),
+        ("new_address", new_address.as_str()),
+        ("status", status),
+    ]))
+}
+
+/// Re-key the grant to the new address, keeping its schedule and claimed_amount,
+/// and move its voting power along
+fn move_vesting_account(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    grant_id: u64,
+    new_address: &Addr,
+) -> Result<(), ContractError> {
+    let mut account = vesting_accounts()
+        .may_load(storage, (address, U64Key::new(grant_id)))?
+        .ok_or(ContractError::VestingNotFound { grant_id })?;
+
+    vesting_accounts().remove(storage, (address, U64Key::new(grant_id)))?;
+    account.address = new_address.clone();
+    vesting_accounts().save(storage, (new_address, U64Key::new(grant_id)), &account)?;
+
+    let voting_power = account.remaining_amount()?;
+    sub_voting_power(
+        storage,
+        height,
+        address,
+        &account.vesting_denom,
+        voting_power,
+    )?;
+    add_voting_power(
+        storage,
+        height,
+        new_address,
+        &account.vesting_denom,
+        voting_power,
+    )?;
+    PENDING_TRANSFERS.remove(storage, U64Key::new(grant_id));
+
+    Ok(())
+}
+
+pub fn receive_cw20(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let amount = cw20_msg.amount;
+    let sender = deps.api.addr_validate(&cw20_msg.sender)?;
+    let contract = info.sender;
+
+    match from_binary(&cw20_msg.msg) {
+        Ok(Cw20HookMsg::RegisterVestingAccount {
+            address,
+            vesting_schedule,
+            time_unit,
+        }) => {
+            let address = deps.api.addr_validate(&address)?;
+            register_vesting_account(
+                deps,
+                env,
+                sender,
+                address,
+                Denom::Cw20(contract),
+                amount,
+                vesting_schedule,
+                time_unit.unwrap_or_default(),
+            )
+        }
+        Ok(Cw20HookMsg::RegisterVestingAccounts { vesting_accounts }) => register_vesting_accounts(
+            deps,
+            env,
+            sender,
+            Denom::Cw20(contract),
+            amount,
+            vesting_accounts,
+        ),
+        Ok(Cw20HookMsg::TopUpVestingAccount {
+            address,
+            grant_id,
+            schedules,
+        }) => {
+            let address = deps.api.addr_validate(&address)?;
+            top_up_vesting_account(
+                deps,
+                env,
+                sender,
+                address,
+                grant_id,
+                Denom::Cw20(contract),
+                amount,
+                schedules,
+            )
+        }
+        Err(_) => Err(ContractError::InvalidCw20HookMsg {}),
+    }
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Config {} => to_binary(&config(deps, env)?),
+        QueryMsg::Roles { address } => to_binary(&roles(deps, env, address)?),
+        QueryMsg::RoleMembers {
+            role,
+            start_after,
+            limit,
+        } => to_binary(&role_members(deps, env, role, start_after, limit)?),
+        QueryMsg::PendingAdmins { start_after, limit } => {
+            to_binary(&pending_admins(deps, env, start_after, limit)?)
+        }
+        QueryMsg::ClaimOperators {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&claim_operators(deps, env, address, start_after, limit)?),
+        QueryMsg::VestingAccount {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&vesting_account(deps, env, address, start_after, limit)?),
+        QueryMsg::AllVestingAccounts { start_after, limit } => {
+            to_binary(&all_vesting_accounts(deps, env, start_after, limit)?)
+        }
+        QueryMsg::LockedBalance { denom } => to_binary(&locked_balance(deps, env, denom)?),
+        QueryMsg::VestingAccountsByDenom {
+            denom,
+            start_after,
+            limit,
+        } => to_binary(&vesting_accounts_by_denom(
+            deps,
+            env,
+            denom,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::ClaimHistory {
+            grant_id,
+            start_after,
+            limit,
+        } => to_binary(&claim_history(deps, env, grant_id, start_after, limit)?),
+        QueryMsg::ArchivedVestingAccounts {
+            address,
+            start_after,
+            limit,
+        } => to_binary(&archived_vesting_accounts(
+            deps,
+            env,
+            address,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::Delegations { address, grant_id } => {
+            to_binary(&delegations(deps, env, address, grant_id)?)
+        }
+        QueryMsg::VotingPower {
+            address,
+            denom,
+            height,
+        } => to_binary(&voting_power(deps, env, address, denom, height)?),
+        QueryMsg::TotalVotingPower { denom, height } => {
+            to_binary(&total_voting_power(deps, env, denom, height)?)
+        }
+        QueryMsg::VestedAmountAt {
+            address,
+            grant_id,
+            time,
+        } => to_binary(&vested_amount_at(deps, env, address, grant_id, time)?),
+        QueryMsg::VestingTimeline {
+            address,
+            grant_id,
+            start_after,
+            limit,
+        } => to_binary(&vesting_timeline(
+            deps,
+            env,
+            address,
+            grant_id,
+            start_after,
+            limit,
+        )?),
+    }
+}
+
+fn config(deps: Deps, _env: Env) -> StdResult<ConfigResponse> {
+    let config = load_config(deps.storage)?;
+    Ok(ConfigResponse {
+        require_transfer_approval: config.require_transfer_approval,
+        unbonding_period: config.unbonding_period,
+        guardian: config.guardian.map(|guardian| guardian.to_string()),
+        paused: config.paused,
+    })
+}
+
+fn roles(deps: Deps, _env: Env, address: String) -> StdResult<RolesResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let roles = Role::ALL
+        .iter()
+        .filter(|role| has_role(deps.storage, **role, &address))
+        .cloned()
+        .collect();
+
+    Ok(RolesResponse {
+        address: address.to_string(),
+        roles,
+    })
+}
+
+fn role_members(
+    deps: Deps,
+    _env: Env,
+    role: Role,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<RoleMembersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?
+        .map(|address| Bound::exclusive(address.as_str()));
+
+    let members = ROLES
+        .prefix(role.key())
+        .keys(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|address| String::from_utf8(address).map_err(StdError::from))
+        .collect::<StdResult<Vec<String>>>()?;
+
+    Ok(RoleMembersResponse { role, members })
+}
+
+fn pending_admins(
+    deps: Deps,
+    _env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<PendingAdminsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|address| deps.api.addr_validate(&address))
+        .transpose()?
+        .map(|address| Bound::exclusive(address.as_str()));
+
+    let pending_admins = PENDING_ADMINS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (address, expires) = item?;
+            Ok(PendingAdminData {
+                address: String::from_utf8(address)?,
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(PendingAdminsResponse { pending_admins })
+}
+
+const MAX_LIMIT: u32 = 30;
+const DEFAULT_LIMIT: u32 = 10;
+fn claim_operators(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ClaimOperatorsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|operator| deps.api.addr_validate(&operator))
+        .transpose()?
+        .map(|operator| Bound::exclusive(operator.as_str()));
+
+    let operators = CLAIM_OPERATORS
+        .prefix(&address)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (operator, expires) = item?;
+            Ok(ClaimOperatorData {
+                operator: String::from_utf8(operator)?,
+                expires,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ClaimOperatorsResponse {
+        recipient: claim_recipient(deps.storage, &address)?.to_string(),
+        address: address.to_string(),
+        operators,
+    })
+}
+
+fn vesting_account(
+    deps: Deps,
+    env: Env,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VestingAccountResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let mut vestings: Vec<VestingData> = vec![];
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    for item in vesting_accounts()
+        .prefix(&address)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive_int),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+    {
+        let (_, account) = item?;
+        vestings.push(vesting_data(account, &env.block)?);
+    }
+
+    Ok(VestingAccountResponse {
+        address: address.to_string(),
+        vestings,
+    })
+}
+
+fn all_vesting_accounts(
+    deps: Deps,
+    env: Env,
+    start_after: Option<(String, u64)>,
+    limit: Option<u32>,
+) -> StdResult<AllVestingAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = vesting_account_bound(deps, start_after)?;
+
+    let vesting_accounts = vesting_accounts()
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, account) = item?;
+            Ok(VestingAccountData {
+                address: account.address.to_string(),
+                vesting: vesting_data(account, &env.block)?,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllVestingAccountsResponse { vesting_accounts })
+}
+
+fn vesting_accounts_by_denom(
+    deps: Deps,
+    env: Env,
+    denom: Denom,
+    start_after: Option<(String, u64)>,
+    limit: Option<u32>,
+) -> StdResult<AllVestingAccountsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = vesting_account_bound(deps, start_after)?;
+
+    let vesting_accounts = vesting_accounts()
+        .idx
+        .denom
+        .prefix(denom_to_key(denom).into_bytes())
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            let (_, account) = item?;
+            Ok(VestingAccountData {
+                address: account.address.to_string(),
+                vesting: vesting_data(account, &env.block)?,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(AllVestingAccountsResponse { vesting_accounts })
+}
+
+fn locked_balance(deps: Deps, env: Env, denom: Denom) -> StdResult<LockedBalanceResponse> {
+    let mut locked = TOTAL_LOCKED
+        .may_load(deps.storage, &denom_to_key(denom.clone()))?
+        .unwrap_or_else(|| LockedBalance::new(denom.clone()));
+    let balance = query_balance(&deps.querier, &env.contract.address, &denom)?;
+    locked.settle_unbonding(balance);
+
+    Ok(LockedBalanceResponse {
+        vesting_denom: locked.vesting_denom,
+        deposited_amount: locked.deposited_amount,
+        claimed_amount: locked.claimed_amount,
+        locked_amount: locked.locked_amount,
+        delegated_amount: locked.delegated_amount,
+        unbonding_amount: locked.unbonding_amount,
+        balance,
+    })
+}
+
+/// Balance of `denom` held by `address`, from the bank module or the cw20 contract
+fn query_balance(querier: &QuerierWrapper, address: &Addr, denom: &Denom) -> StdResult<Uint128> {
+    match denom {
+        Denom::Native(denom) => Ok(querier.query_balance(address, denom)?.amount),
+        Denom::Cw20(contract_addr) => {
+            let res: Cw20BalanceResponse = querier.query_wasm_smart(
+                contract_addr,
+                &Cw20QueryMsg::Balance {
+                    address: address.to_string(),
+                },
+            )?;
+            Ok(res.balance)
+        }
+    }
+}
+
+/// Undelegated tokens of the denom back in the balance so far.
+/// The balance is only checked while some tokens are on the way back.
+fn settle_unbonding(
+    storage: &mut dyn Storage,
+    querier: &QuerierWrapper,
+    contract_addr: &Addr,
+    denom: &Denom,
+) -> StdResult<Uint128> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED
+        .may_load(storage, &denom_key)?
+        .unwrap_or_else(|| LockedBalance::new(denom.clone()));
+    if !locked.unbonding_amount.is_zero() {
+        locked.settle_unbonding(query_balance(querier, contract_addr, denom)?);
+        TOTAL_LOCKED.save(storage, &denom_key, &locked)?;
+    }
+
+    Ok(locked.total_returned())
+}
+
+/// Exclusive start bound on the (address, grant_id) primary key
+fn vesting_account_bound(
+    deps: Deps,
+    start_after: Option<(String, u64)>,
+) -> StdResult<Option<Bound>> {
+    start_after
+        .map(|(address, grant_id)| {
+            let address = deps.api.addr_validate(&address)?;
+            Ok(Bound::exclusive(
+                (&address, U64Key::new(grant_id)).joined_key(),
+            ))
+        })
+        .transpose()
+}
+
+fn claim_history(
+    deps: Deps,
+    _env: Env,
+    grant_id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ClaimHistoryResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let claims = CLAIM_HISTORY
+        .prefix(U64Key::new(grant_id))
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive_int),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (claim_id, record) = item?;
+            Ok(ClaimRecordData {
+                claim_id: u64::from_be_bytes(claim_id.as_slice().try_into().unwrap()),
+                timestamp: record.timestamp,
+                height: record.height,
+                amount: record.amount,
+                recipient: record.recipient.to_string(),
+                kind: record.kind,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ClaimHistoryResponse { grant_id, claims })
+}
+
+fn archived_vesting_accounts(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<ArchivedVestingAccountsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let vestings = ARCHIVED_ACCOUNTS
+        .prefix(&address)
+        .range(
+            deps.storage,
+            start_after.map(Bound::exclusive_int),
+            None,
+            Order::Ascending,
+        )
+        .take(limit)
+        .map(|item| {
+            let (_, archived) = item?;
+            let account = archived.account;
+            Ok(ArchivedVestingData {
+                grant_id: account.grant_id,
+                vesting_denom: account.vesting_denom,
+                vesting_amount: account.vesting_amount,
+                claimed_amount: account.claimed_amount,
+                vesting_schedule: account.vesting_schedule,
+                time_unit: account.time_unit,
+                revoked_at: account.revoked_at,
+                paused_periods: account.paused_periods,
+                reason: archived.reason,
+                archived_at: archived.archived_at,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(ArchivedVestingAccountsResponse {
+        address: address.to_string(),
+        vestings,
+    })
+}
+
+fn voting_power(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    denom: Option<Denom>,
+    height: Option<u64>,
+) -> StdResult<VotingPowerResponse> {
+    let address = deps.api.addr_validate(&address)?;
+
+    // the entries are kept at zero, so every denom held in the past is listed
+    let denom_keys = match denom {
+        Some(denom) => vec![denom_to_key(denom)],
+        None => VOTING_POWER
+            .prefix(&address)
+            .keys(deps.storage, None, None, Order::Ascending)
+            .map(|denom_key| String::from_utf8(denom_key).map_err(StdError::from))
+            .collect::<StdResult<Vec<String>>>()?,
+    };
+
+    let voting_powers = denom_keys
+        .into_iter()
+        .map(|denom_key| {
+            let key = (&address, denom_key.as_str());
+            let voting_power = match height {
+                Some(height) => VOTING_POWER.may_load_at_height(deps.storage, key, height)?,
+                None => VOTING_POWER.may_load(deps.storage, key)?,
+            }
+            .unwrap_or_default();
+            Ok(DenomVotingPower {
+                denom: key_to_denom(&denom_key)?,
+                voting_power,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(VotingPowerResponse {
+        address: address.to_string(),
+        height,
+        voting_powers,
+    })
+}
+
+fn total_voting_power(
+    deps: Deps,
+    _env: Env,
+    denom: Option<Denom>,
+    height: Option<u64>,
+) -> StdResult<TotalVotingPowerResponse> {
+    let denom_keys = match denom {
+        Some(denom) => vec![denom_to_key(denom)],
+        None => TOTAL_VOTING_POWER
+            .range(deps.storage, None, None, Order::Ascending)
+            .map(|item| {
+                let (denom_key, _) = item?;
+                String::from_utf8(denom_key).map_err(StdError::from)
+            })
+            .collect::<StdResult<Vec<String>>>()?,
+    };
+
+    let voting_powers = denom_keys
+        .into_iter()
+        .map(|denom_key| {
+            let voting_power = match height {
+                Some(height) => {
+                    TOTAL_VOTING_POWER.may_load_at_height(deps.storage, &denom_key, height)?
+                }
+                None => TOTAL_VOTING_POWER.may_load(deps.storage, &denom_key)?,
+            }
+            .unwrap_or_default();
+            Ok(DenomVotingPower {
+                denom: key_to_denom(&denom_key)?,
+                voting_power,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(TotalVotingPowerResponse {
+        height,
+        voting_powers,
+    })
+}
+
+fn delegations(
+    deps: Deps,
+    env: Env,
+    address: String,
+    grant_id: u64,
+) -> StdResult<DelegationsResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let account = vesting_accounts().load(deps.storage, (&address, U64Key::new(grant_id)))?;
+
+    let mut rewards = account.rewards;
+    let mut slashed_amount = account.slashed_amount;
+    let mut delegations: Vec<DelegationData> = vec![];
+    for item in GRANT_DELEGATIONS.prefix(U64Key::new(grant_id)).range(
+        deps.storage,
+        None,
+        None,
+        Order::Ascending,
+    ) {
+        let (validator, mut position) = item?;
+        let validator = String::from_utf8(validator)?;
+
+        // include the slashing and the rewards not yet settled with the validator
+        let mut validator_position = VALIDATOR_DELEGATIONS.load(deps.storage, &validator)?;
+        let delegation = deps
+            .querier
+            .query_delegation(&env.contract.address, &validator)?;
+        let bonded_denom = deps.querier.query_bonded_denom()?;
+        reconcile_validator(&mut validator_position, delegation.as_ref(), &bonded_denom);
+        let unslashed_amount = position.unslashed_amount(&validator_position);
+        slashed_amount += position.amount.checked_sub(unslashed_amount)?;
+        position.amount = unslashed_amount;
+        rewards += position.amount * (validator_position.reward_index - position.reward_index);
+        if position.amount.is_zero() {
+            continue;
+        }
+
+        delegations.push(DelegationData {
+            validator,
+            amount: position.amount,
+        });
+    }
+
+    // the unbondings are back once the balance covers them
+    let mut locked =
+        TOTAL_LOCKED.load(deps.storage, &denom_to_key(account.vesting_denom.clone()))?;
+    if !locked.unbonding_amount.is_zero() {
+        locked.settle_unbonding(query_balance(
+            &deps.querier,
+            &env.contract.address,
+            &account.vesting_denom,
+        )?);
+    }
+    let returned_amount = locked.total_returned();
+    let now = env.block.time.seconds();
+    Ok(DelegationsResponse {
+        address: address.to_string(),
+        grant_id,
+        delegations,
+        unbondings: account
+            .unbondings
+            .into_iter()
+            .filter(|unbonding| !unbonding.is_returned(now, returned_amount))
+            .collect(),
+        rewards,
+        slashed_amount,
+    })
+}
+
+fn vested_amount_at(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    grant_id: u64,
+    time: u64,
+) -> StdResult<VestedAmountAtResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let account = vesting_accounts().load(deps.storage, (&address, U64Key::new(grant_id)))?;
+    let vested_amount = account.vested_amount_at(time)?;
+
+    Ok(VestedAmountAtResponse {
+        address: address.to_string(),
+        grant_id,
+        time,
+        time_unit: account.time_unit,
+        vested_amount,
+        claimable_amount: vested_amount
+            .saturating_sub(account.claimed_amount)
+            .saturating_sub(account.slashed_amount),
+    })
+}
+
+fn vesting_timeline(
+    deps: Deps,
+    _env: Env,
+    address: String,
+    grant_id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<VestingTimelineResponse> {
+    let address = deps.api.addr_validate(&address)?;
+    let account = vesting_accounts().load(deps.storage, (&address, U64Key::new(grant_id)))?;
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+
+    let breakpoints = account
+        .breakpoints(start_after, limit)
+        .into_iter()
+        .map(|time| {
+            Ok(VestingBreakpoint {
+                time,
+                vested_amount: account.vested_amount_at(time)?,
+            })
+        })
+        .collect::<StdResult<Vec<VestingBreakpoint>>>()?;
+
+    Ok(VestingTimelineResponse {
+        address: address.to_string(),
+        grant_id,
+        time_unit: account.time_unit,
+        breakpoints,
+    })
+}
+
+fn vesting_data(account: VestingAccount, block: &BlockInfo) -> StdResult<VestingData> {
+    let vested_amount = account.vested_amount(block)?;
+    Ok(VestingData {
+        grant_id: account.grant_id,
+        vesting_denom: account.vesting_denom,
+        vesting_amount: account.vesting_amount,
+        vested_amount,
+        vesting_schedule: account.vesting_schedule,
+        time_unit: account.time_unit,
+        revoked_at: account.revoked_at,
+        paused_periods: account.paused_periods,
+        claimable_amount: vested_amount
+            .checked_sub(account.claimed_amount)?
+            .saturating_sub(account.slashed_amount),
+    })
+}
+
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(deps: DepsMut, env: Env, msg: MigrateMsg) -> Result<Response, ContractError> {
+    // contracts deployed before cw2 have no contract info
+    let contract_info = cw2::CONTRACT.may_load(deps.storage)?;
+    if let Some(contract_info) = &contract_info {
+        if contract_info.contract != CONTRACT_NAME {
+            return Err(ContractError::CannotMigrate {
+                previous_contract: contract_info.contract.clone(),
+            });
+        }
+    }
+
+    // the stored layout decides the migration path, and the message must agree with it;
+    // contracts deployed before the layout was stored are v1 or v2 without cw2 info,
+    // and v2 or later with it
+    let requested = msg.layout_version();
+    match LAYOUT_VERSION.may_load(deps.storage)? {
+        Some(stored) if stored != requested => {
+            return Err(ContractError::LayoutMismatch { stored, requested })
+        }
+        Some(_) => {}
+        None if contract_info.is_none() && requested > 2 => {
+            return Err(ContractError::InvalidLayout { requested })
+        }
+        None if contract_info.is_some() && requested == 1 => {
+            return Err(ContractError::InvalidLayout { requested })
+        }
+        None => {}
+    }
+
+    if requested <= 1 {
+        migrate_from_v1(deps.storage)?;
+    }
+    if requested <= 2 {
+        migrate_from_v2(deps.storage)?;
+    }
+    if requested <= 3 {
+        migrate_from_v3(deps.storage, env.block.height)?;
+    }
+    if requested <= 4 {
+        migrate_from_v4(deps.storage)?;
+    }
+
+    LAYOUT_VERSION.save(deps.storage, &CURRENT_LAYOUT_VERSION)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate"),
+        ("contract_name", CONTRACT_NAME),
+        ("contract_version", CONTRACT_VERSION),
+    ]))
 }
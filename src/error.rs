@@ -0,0 +1,169 @@
+use cosmwasm_std::{OverflowError, StdError, Uint128};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ContractError {
+    #[error("{0}")]
+    Std(#[from] StdError),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("Unauthorized")]
+    Unauthorized {},
+
+    #[error("Claims are paused")]
+    ClaimsPaused {},
+
+    #[error("Registrations are paused")]
+    RegistrationsPaused {},
+
+    #[error("Deregistrations are paused")]
+    DeregistrationsPaused {},
+
+    #[error("Cannot migrate from different contract type: {previous_contract}")]
+    CannotMigrate { previous_contract: String },
+
+    #[error("Cannot migrate from layout v{requested}, the contract is in layout v{stored}")]
+    LayoutMismatch { stored: u64, requested: u64 },
+
+    #[error("Cannot migrate from layout v{requested}, which does not match the contract")]
+    InvalidLayout { requested: u64 },
+
+    #[error("Expiration is already expired")]
+    InvalidExpiration {},
+
+    #[error("{address} does not hold the {role} role")]
+    RoleNotFound { role: String, address: String },
+
+    #[error("Cannot revoke the last admin")]
+    LastAdmin {},
+
+    #[error("The admin role must be proposed and accepted")]
+    AdminRequiresAcceptance {},
+
+    #[error("No pending admin proposal for {address}")]
+    NoPendingAdmin { address: String },
+
+    #[error("Admin proposal is expired")]
+    AdminProposalExpired {},
+
+    #[error("Must deposit only one type of token")]
+    InvalidFunds {},
+
+    #[error("Vesting accounts must not be empty")]
+    EmptyVestingAccounts {},
+
+    #[error("Invalid cw20 hook message")]
+    InvalidCw20HookMsg {},
+
+    #[error("Vesting entry is not found for grant {grant_id}")]
+    VestingNotFound { grant_id: u64 },
+
+    #[error("Claim operator approval is expired")]
+    ClaimOperatorExpired {},
+
+    #[error("Vesting grant {grant_id} is revoked")]
+    VestingRevoked { grant_id: u64 },
+
+    #[error("Vesting grant {grant_id} is paused")]
+    VestingPaused { grant_id: u64 },
+
+    #[error("Vesting grant {grant_id} is not paused")]
+    VestingNotPaused { grant_id: u64 },
+
+    #[error("No pending transfer for grant {grant_id}")]
+    NoPendingTransfer { grant_id: u64 },
+
+    #[error("Vesting entry is not found for denom {denom}")]
+    DenomNotFound { denom: String },
+
+    #[error("No surplus to sweep for denom {denom}")]
+    NoSurplus { denom: String },
+
+    #[error("Deposit amount must be greater than zero")]
+    ZeroDepositAmount {},
+
+    #[error("Vesting amount must be greater than zero")]
+    ZeroVestingAmount {},
+
+    #[error("Release amount must be greater than zero")]
+    ZeroReleaseAmount {},
+
+    #[error("Cliff schedules must not be empty")]
+    EmptyCliffSchedules {},
+
+    #[error("{name} {time} must not be before block time {block_time}")]
+    TimeBeforeBlockTime {
+        name: String,
+        time: u64,
+        block_time: u64,
+    },
+
+    #[error("End time {end_time} must not be before start time {start_time}")]
+    EndTimeBeforeStartTime { start_time: u64, end_time: u64 },
+
+    #[error("Vesting interval must not be zero")]
+    ZeroVestingInterval {},
+
+    #[error(
+        "Vesting period {vesting_period} must be multiple of vesting interval {vesting_interval}"
+    )]
+    InvalidVestingInterval {
+        vesting_period: u64,
+        vesting_interval: u64,
+    },
+
+    #[error("Cliff amount {cliff_amount} must not exceed vesting amount {vesting_amount}")]
+    InvalidCliffAmount {
+        cliff_amount: Uint128,
+        vesting_amount: Uint128,
+    },
+
+    #[error("Cliff schedules are only allowed for cliff vesting")]
+    UnexpectedCliffSchedules {},
+
+    #[error("Top-up amount {top_up_amount} must be multiple of interval amount {interval_amount}")]
+    InvalidTopUpAmount {
+        top_up_amount: Uint128,
+        interval_amount: Uint128,
+    },
+
+    #[error("Deposit denom {deposit_denom} must be equal to vesting denom {vesting_denom}")]
+    VestingDenomMismatch {
+        deposit_denom: String,
+        vesting_denom: String,
+    },
+
+    #[error("Deposit amount {deposit_amount} must be equal to vesting amount {vesting_amount}")]
+    DepositAmountMismatch {
+        deposit_amount: Uint128,
+        vesting_amount: Uint128,
+    },
+
+    #[error("Staking is only available for native grants of the bonded denom, not {denom}")]
+    InvalidStakingDenom { denom: String },
+
+    #[error("Amount {amount} exceeds the unvested amount {available} which is not staked")]
+    ExceedsUnvestedAmount { amount: Uint128, available: Uint128 },
+
+    #[error("Amount {amount} exceeds the delegation {delegated} to {validator}")]
+    ExceedsDelegation {
+        validator: String,
+        amount: Uint128,
+        delegated: Uint128,
+    },
+
+    #[error("Vesting grant {grant_id} has delegated or unbonding tokens")]
+    VestingStaked { grant_id: u64 },
+
+    #[error("Validator {validator} lost every delegated token to slashing")]
+    ValidatorSlashed { validator: String },
+
+    #[error("Amount {amount} exceeds the overdue unbondings {overdue} of grant {grant_id}")]
+    ExceedsOverdueUnbonding {
+        grant_id: u64,
+        amount: Uint128,
+        overdue: Uint128,
+    },
+}
@@ -1,6 +1,10 @@
 pub mod contract;
+pub mod error;
+pub mod migrations;
 pub mod msg;
 pub mod state;
 
+#[cfg(test)]
+mod mock_querier;
 #[cfg(test)]
 mod testing;
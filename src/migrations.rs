@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::msg::{Role, TimeUnit, VestingSchedule};
+use crate::state::{
+    denom_to_key, next_grant_id, vesting_accounts, LockedBalance, VestingAccount, PENDING_ADMINS,
+    ROLES, TOTAL_LOCKED, TOTAL_VOTING_POWER, VOTING_POWER,
+};
+use cosmwasm_std::{Addr, Empty, Order, StdError, StdResult, Storage, Uint128};
+use cw20::{Denom, Expiration};
+use cw_storage_plus::{Item, Map, U64Key};
+
+/// VESTING_ACCOUNTS_V1 is the layout before grant ids,
+/// which allowed only one vesting account per (address, denom_key)
+pub const VESTING_ACCOUNTS_V1: Map<(&str, &str), VestingAccountV1> = Map::new("vesting_accounts");
+
+/// MASTER_ADDRESS_V4 is the single address which held every privilege before the roles
+pub const MASTER_ADDRESS_V4: Item<Addr> = Item::new("master_address");
+pub const PENDING_MASTER_ADDRESS_V4: Item<PendingMasterAddressV4> =
+    Item::new("pending_master_address");
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PendingMasterAddressV4 {
+    pub master_address: Addr,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct VestingAccountV1 {
+    pub address: String,
+    pub vesting_denom: Denom,
+    pub vesting_amount: Uint128,
+    pub vesting_schedule: VestingSchedule,
+    pub claimed_amount: Uint128,
+}
+
+/// Rewrite every (address, denom_key) entry into a new grant keyed by (address, grant_id)
+pub fn migrate_from_v1(storage: &mut dyn Storage) -> StdResult<()> {
+    let accounts = VESTING_ACCOUNTS_V1
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Vec<u8>, VestingAccountV1)>>>()?;
+
+    for (key, account) in accounts {
+        // remove the entry under the key it is stored with
+        let (address_key, denom_key) = split_key(&key)?;
+        VESTING_ACCOUNTS_V1.remove(storage, (&address_key, &denom_key));
+
+        // the address was already accepted by the former contract
+        let address = Addr::unchecked(account.address);
+        let grant_id = next_grant_id(storage)?;
+        vesting_accounts().save(
+            storage,
+            (&address, U64Key::new(grant_id)),
+            &VestingAccount {
+                address: address.clone(),
+                grant_id,
+                vesting_denom: account.vesting_denom,
+                vesting_amount: account.vesting_amount,
+                vesting_schedule: account.vesting_schedule,
+                claimed_amount: account.claimed_amount,
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+                delegated_amount: Uint128::zero(),
+                unbondings: vec![],
+                rewards: Uint128::zero(),
+                slashed_amount: Uint128::zero(),
+            },
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Decode an (address, denom_key) key,
+/// where the address is prefixed by its length in two bytes
+fn split_key(key: &[u8]) -> StdResult<(String, String)> {
+    let invalid_key = || StdError::generic_err("Invalid (address, denom_key) key");
+    let (length, key) = (key.get(..2).ok_or_else(invalid_key)?, &key[2..]);
+    let length = u16::from_be_bytes([length[0], length[1]]) as usize;
+    if key.len() < length {
+        return Err(invalid_key());
+    }
+
+    let (address, denom_key) = key.split_at(length);
+    Ok((
+        String::from_utf8(address.to_vec())?,
+        String::from_utf8(denom_key.to_vec())?,
+    ))
+}
+
+/// Re-save every vesting account so that the denom index is built for them,
+/// and recompute the locked balances from the grants left
+pub fn migrate_from_v2(storage: &mut dyn Storage) -> StdResult<()> {
+    let accounts = vesting_accounts()
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, account)| account))
+        .collect::<StdResult<Vec<VestingAccount>>>()?;
+
+    let mut totals: BTreeMap<String, LockedBalance> = BTreeMap::new();
+    for account in accounts {
+        let locked = totals
+            .entry(denom_to_key(account.vesting_denom.clone()))
+            .or_insert_with(|| LockedBalance::new(account.vesting_denom.clone()));
+        locked.deposited_amount = locked
+            .deposited_amount
+            .checked_add(account.vesting_amount)?;
+        locked.claimed_amount = locked.claimed_amount.checked_add(account.claimed_amount)?;
+        locked.locked_amount = locked
+            .locked_amount
+            .checked_add(account.vesting_amount.checked_sub(account.claimed_amount)?)?;
+
+        vesting_accounts().save(
+            storage,
+            (&account.address, U64Key::new(account.grant_id)),
+            &account,
+        )?;
+    }
+
+    for (denom_key, locked) in totals {
+        TOTAL_LOCKED.save(storage, &denom_key, &locked)?;
+    }
+
+    Ok(())
+}
+
+/// Rebuild the voting power of every address from the grants left,
+/// which is snapshotted from the migration height.
+/// Powers stored before are overwritten, and zeroed when no grant is left for them.
+pub fn migrate_from_v3(storage: &mut dyn Storage, height: u64) -> StdResult<()> {
+    let accounts = vesting_accounts()
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, account)| account))
+        .collect::<StdResult<Vec<VestingAccount>>>()?;
+
+    let mut powers: BTreeMap<(Addr, String), Uint128> = BTreeMap::new();
+    let mut totals: BTreeMap<String, Uint128> = BTreeMap::new();
+    for key in VOTING_POWER
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(key, _)| key))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?
+    {
+        let (address, denom_key) = split_key(&key)?;
+        powers.insert((Addr::unchecked(address), denom_key), Uint128::zero());
+    }
+    for key in TOTAL_VOTING_POWER
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(key, _)| key))
+        .collect::<StdResult<Vec<Vec<u8>>>>()?
+    {
+        totals.insert(String::from_utf8(key)?, Uint128::zero());
+    }
+
+    for account in accounts {
+        let denom_key = denom_to_key(account.vesting_denom.clone());
+        let amount = account.remaining_amount()?;
+
+        let power = powers
+            .entry((account.address.clone(), denom_key.clone()))
+            .or_default();
+        *power = power.checked_add(amount)?;
+        let total = totals.entry(denom_key).or_default();
+        *total = total.checked_add(amount)?;
+    }
+
+    for ((address, denom_key), power) in powers {
+        VOTING_POWER.save(storage, (&address, &denom_key), &power, height)?;
+    }
+    for (denom_key, total) in totals {
+        TOTAL_VOTING_POWER.save(storage, &denom_key, &total, height)?;
+    }
+
+    Ok(())
+}
+
+/// Grant every role to the master address, and keep its pending proposal as an admin proposal
+pub fn migrate_from_v4(storage: &mut dyn Storage) -> StdResult<()> {
+    // nothing to do once the master address is migrated
+    let master_address = match MASTER_ADDRESS_V4.may_load(storage)? {
+        Some(master_address) => master_address,
+        None => return Ok(()),
+    };
+    for role in Role::ALL {
+        ROLES.save(storage, (role.key(), &master_address), &Empty {})?;
+    }
+
+    if let Some(pending) = PENDING_MASTER_ADDRESS_V4.may_load(storage)? {
+        PENDING_ADMINS.save(storage, &pending.master_address, &pending.expires)?;
+    }
+
+    MASTER_ADDRESS_V4.remove(storage);
+    PENDING_MASTER_ADDRESS_V4.remove(storage);
+    Ok(())
+}
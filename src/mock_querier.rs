@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+
+use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+use cosmwasm_std::{
+    from_binary, from_slice, to_binary, Coin, ContractResult, Empty, FullDelegation, OwnedDeps,
+    Querier, QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
+};
+use cw20::{BalanceResponse as Cw20BalanceResponse, Cw20QueryMsg};
+
+/// mock_dependencies is a drop-in replacement for cosmwasm_std::testing::mock_dependencies
+/// this uses our WasmMockQuerier, which also answers cw20 balance queries
+pub fn mock_dependencies(
+    contract_balance: &[Coin],
+) -> OwnedDeps<MockStorage, MockApi, WasmMockQuerier> {
+    let custom_querier =
+        WasmMockQuerier::new(MockQuerier::new(&[(MOCK_CONTRACT_ADDR, contract_balance)]));
+
+    OwnedDeps {
+        storage: MockStorage::default(),
+        api: MockApi::default(),
+        querier: custom_querier,
+    }
+}
+
+pub struct WasmMockQuerier {
+    base: MockQuerier<Empty>,
+    token_balances: HashMap<String, HashMap<String, Uint128>>,
+}
+
+impl Querier for WasmMockQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(v) => v,
+            Err(e) => {
+                return SystemResult::Err(SystemError::InvalidRequest {
+                    error: format!("Parsing query request: {}", e),
+                    request: bin_request.into(),
+                })
+            }
+        };
+        self.handle_query(&request)
+    }
+}
+
+impl WasmMockQuerier {
+    pub fn new(base: MockQuerier<Empty>) -> Self {
+        WasmMockQuerier {
+            base,
+            token_balances: HashMap::new(),
+        }
+    }
+
+    pub fn handle_query(&self, request: &QueryRequest<Empty>) -> QuerierResult {
+        match request {
+            QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => match from_binary(msg) {
+                Ok(Cw20QueryMsg::Balance { address }) => {
+                    let balance = self
+                        .token_balances
+                        .get(contract_addr)
+                        .and_then(|balances| balances.get(&address))
+                        .cloned()
+                        .unwrap_or_default();
+                    SystemResult::Ok(ContractResult::Ok(
+                        to_binary(&Cw20BalanceResponse { balance }).unwrap(),
+                    ))
+                }
+                _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                    kind: "cw20 query".to_string(),
+                }),
+            },
+            _ => self.base.handle_query(request),
+        }
+    }
+
+    // configure the token balances of the given cw20 contracts
+    pub fn with_token_balances(&mut self, balances: &[(&str, &[(&str, Uint128)])]) {
+        for (contract_addr, balances) in balances.iter() {
+            self.token_balances.insert(
+                contract_addr.to_string(),
+                balances
+                    .iter()
+                    .map(|(address, balance)| (address.to_string(), *balance))
+                    .collect(),
+            );
+        }
+    }
+
+    // set a new native balance for the given address
+    pub fn update_balance(&mut self, addr: impl Into<String>, balance: Vec<Coin>) {
+        self.base.update_balance(addr, balance);
+    }
+
+    // set the bonded denom and the delegations of the staking module
+    pub fn update_staking(&mut self, denom: &str, delegations: &[FullDelegation]) {
+        self.base.update_staking(denom, &[], delegations);
+    }
+}
@@ -1,11 +1,20 @@
-use cosmwasm_std::{StdError, StdResult, Uint128};
-use cw20::{Cw20ReceiveMsg, Denom};
+use std::convert::TryFrom;
+
+use cosmwasm_std::{BlockInfo, StdResult, Uint128, Uint64};
+use cw20::{Cw20ReceiveMsg, Denom, Expiration};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::error::ContractError;
+
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct InstantiateMsg {
+    /// granted every role, the instantiator if not given
     pub master_address: Option<String>,
+    /// transfers of vesting accounts wait for an admin approval, false by default
+    pub require_transfer_approval: Option<bool>,
+    /// can pause claims, registrations and deregistrations in an emergency
+    pub guardian: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -16,28 +25,169 @@ pub enum ExecuteMsg {
     //////////////////////////
     /// Creator Operations ///
     //////////////////////////
+    /// only available from a registrar
     RegisterVestingAccount {
         address: String,
         vesting_schedule: VestingSchedule,
+        /// the unit of the schedule times, seconds by default
+        time_unit: Option<TimeUnit>,
+    },
+    /// register multiple vesting accounts with a single deposit,
+    /// which must be equal with sum of all vesting amounts
+    RegisterVestingAccounts {
+        vesting_accounts: Vec<NewVestingAccount>,
+    },
+    /// add the deposit to the vesting schedule of an existing grant,
+    /// `schedules` are the new entries of a cliff vesting
+    TopUpVestingAccount {
+        address: String,
+        grant_id: u64,
+        schedules: Option<Vec<CliffSchedule>>,
+    },
+    /// stop the grant vesting, sending the unvested amount to the recipient or the revoker;
+    /// the vested amount stays claimable by the vesting account, only available from a revoker
+    RevokeVestingAccount {
+        address: String,
+        grant_id: u64,
+        left_vesting_token_recipient: Option<String>,
+    },
+    /// stop the vesting clock of the grant until resumed,
+    /// which postpones the rest of the schedule by the paused duration
+    PauseVesting {
+        address: String,
+        grant_id: u64,
+    },
+    ResumeVesting {
+        address: String,
+        grant_id: u64,
+    },
+    /// undelegate all tokens of the grant, e.g. before it is revoked or deregistered
+    ForceUndelegate {
+        address: String,
+        grant_id: u64,
     },
-    /// only available when master_address was set
+    /// only available from a revoker
     DeregisterVestingAccount {
         address: String,
-        denom: Denom,
+        grant_id: u64,
         vested_token_recipient: Option<String>,
         left_vesting_token_recipient: Option<String>,
     },
-    UpdateMasterAddress {
-        master_address: String,
+    /// add the address to the role, only available from an admin
+    /// and not for the admin role, which is proposed instead
+    GrantRole {
+        role: Role,
+        address: String,
+    },
+    /// propose the address as an admin, which takes effect
+    /// only after the proposed address accepts it
+    ProposeAdmin {
+        address: String,
+        expires: Option<Expiration>,
+    },
+    /// only available from a proposed admin
+    AcceptAdmin {},
+    CancelAdminProposal {
+        address: String,
+    },
+    /// remove the address from the role, which cannot remove the last admin
+    RevokeRole {
+        role: Role,
+        address: String,
+    },
+    UpdateConfig {
+        require_transfer_approval: Option<bool>,
+        unbonding_period: Option<u64>,
+    },
+    /// set the guardian, or remove it if not given
+    UpdateGuardian {
+        guardian: Option<String>,
+    },
+    /// the guardian can only pause, an admin can both pause and unpause
+    SetPaused {
+        claims: Option<bool>,
+        registrations: Option<bool>,
+        deregistrations: Option<bool>,
+    },
+    /// approve the pending transfer of a grant
+    ApproveVestingTransfer {
+        grant_id: u64,
+    },
+    /// send the contract balance of the denom which is not
+    /// backing any vesting account to the recipient, or the admin
+    SweepSurplus {
+        denom: Denom,
+        recipient: Option<String>,
+    },
+    /// write off the amount slashed from the overdue unbondings of a grant,
+    /// which never comes back to the contract
+    WriteOffUnbonding {
+        address: String,
+        grant_id: u64,
+        amount: Uint128,
     },
 
     ////////////////////////
     /// VestingAccount Operations ///
     ////////////////////////
+    /// claim all grants of the given denoms,
+    /// or only the given grant_ids of them if specified
     Claim {
         denoms: Vec<Denom>,
+        grant_ids: Option<Vec<u64>>,
+        recipient: Option<String>,
+    },
+    /// claim every grant of the sender, skipping grants with nothing to claim
+    ClaimAll {
+        recipient: Option<String>,
+    },
+    /// claim on behalf of the address, only available from its approved operators;
+    /// the tokens are sent to the registered claim recipient or the address
+    ClaimFor {
+        address: String,
+        denoms: Vec<Denom>,
+        grant_ids: Option<Vec<u64>>,
+    },
+    /// allow the operator to ClaimFor the sender until expires, never by default
+    ApproveClaimOperator {
+        operator: String,
+        expires: Option<Expiration>,
+    },
+    RevokeClaimOperator {
+        operator: String,
+    },
+    /// set the default recipient of claims, or the sender itself if not given
+    RegisterClaimRecipient {
         recipient: Option<String>,
     },
+    /// move the grant to the new address, with its schedule and claimed_amount,
+    /// or wait for an admin approval if require_transfer_approval is set
+    TransferVestingAccount {
+        grant_id: u64,
+        new_address: String,
+    },
+    /// delegate the unvested tokens of a native grant in the bonded denom
+    Delegate {
+        grant_id: u64,
+        validator: String,
+        amount: Uint128,
+    },
+    /// the tokens are back to the grant after the unbonding_period
+    Undelegate {
+        grant_id: u64,
+        validator: String,
+        amount: Uint128,
+    },
+    Redelegate {
+        grant_id: u64,
+        src_validator: String,
+        dst_validator: String,
+        amount: Uint128,
+    },
+    /// send the staking rewards of the grant to the claim recipient
+    WithdrawRewards {
+        grant_id: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -47,23 +197,227 @@ pub enum Cw20HookMsg {
     RegisterVestingAccount {
         address: String,
         vesting_schedule: VestingSchedule,
+        /// the unit of the schedule times, seconds by default
+        time_unit: Option<TimeUnit>,
+    },
+    /// Register multiple vesting accounts with token transfer
+    RegisterVestingAccounts {
+        vesting_accounts: Vec<NewVestingAccount>,
     },
+    /// Top up an existing grant with token transfer
+    TopUpVestingAccount {
+        address: String,
+        grant_id: u64,
+        schedules: Option<Vec<CliffSchedule>>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NewVestingAccount {
+    pub address: String,
+    pub vesting_schedule: VestingSchedule,
+    pub time_unit: Option<TimeUnit>,
+}
+
+/// MigrateMsg selects the storage layout version the contract is migrated from
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrateMsg {
+    /// Layout before grant ids, which stored one vesting account per (address, denom)
+    FromV1 {},
+    /// Layout with grant ids but without the denom index and locked balances
+    FromV2 {},
+    /// Layout without the voting power snapshots
+    FromV3 {},
+    /// Layout with a single master address in place of the roles
+    FromV4 {},
+    /// Current layout, only the contract version is updated
+    FromV5 {},
+}
+
+impl MigrateMsg {
+    pub fn layout_version(&self) -> u64 {
+        match self {
+            MigrateMsg::FromV1 {} => 1,
+            MigrateMsg::FromV2 {} => 2,
+            MigrateMsg::FromV3 {} => 3,
+            MigrateMsg::FromV4 {} => 4,
+            MigrateMsg::FromV5 {} => 5,
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
-    MasterAddress {},
+    Config {},
+    /// Roles held by an address
+    Roles {
+        address: String,
+    },
+    /// Addresses holding a role, ordered by address
+    RoleMembers {
+        role: Role,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Admin proposals waiting for an acceptance, ordered by address
+    PendingAdmins {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Claim recipient and operators of an address, ordered by operator
+    ClaimOperators {
+        address: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     VestingAccount {
         address: String,
-        start_after: Option<Denom>,
+        start_after: Option<u64>,
         limit: Option<u32>,
     },
+    /// Lists grants of every address, ordered by (address, grant_id)
+    AllVestingAccounts {
+        start_after: Option<(String, u64)>,
+        limit: Option<u32>,
+    },
+    /// Totals of one denom against the contract balance
+    LockedBalance {
+        denom: Denom,
+    },
+    /// Lists grants of one denom, ordered by (address, grant_id)
+    VestingAccountsByDenom {
+        denom: Denom,
+        start_after: Option<(String, u64)>,
+        limit: Option<u32>,
+    },
+    /// Vested amount of a grant at the given time in its time_unit,
+    /// assuming an ongoing pause is not resumed
+    VestedAmountAt {
+        address: String,
+        grant_id: u64,
+        time: u64,
+    },
+    /// Times where the vested amount of a grant changes, with the
+    /// cumulative vested amount at each of them, ordered by time
+    VestingTimeline {
+        address: String,
+        grant_id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Claims of a grant, ordered by claim_id
+    ClaimHistory {
+        grant_id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Removed grants of an address, ordered by grant_id
+    ArchivedVestingAccounts {
+        address: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Delegations, pending unbondings and staking rewards of a grant
+    Delegations {
+        address: String,
+        grant_id: u64,
+    },
+    /// Vesting amount minus claimed amount of the grants of an address in one denom
+    /// or per denom, at the start of the block height or currently; snapshots are by height only
+    VotingPower {
+        address: String,
+        denom: Option<Denom>,
+        height: Option<u64>,
+    },
+    /// Voting power of all addresses in one denom or per denom,
+    /// at the start of the block height or currently
+    TotalVotingPower {
+        denom: Option<Denom>,
+        height: Option<u64>,
+    },
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
-pub struct MasterAddressResponse {
-    pub master_address: String,
+pub struct ConfigResponse {
+    pub require_transfer_approval: bool,
+    pub unbonding_period: u64,
+    pub guardian: Option<String>,
+    pub paused: PausedOperations,
+}
+
+/// Operations halted by the global pause
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+pub struct PausedOperations {
+    /// Claim, ClaimAll, ClaimFor and WithdrawRewards
+    pub claims: bool,
+    /// RegisterVestingAccount(s) and TopUpVestingAccount
+    pub registrations: bool,
+    /// DeregisterVestingAccount and RevokeVestingAccount
+    pub deregistrations: bool,
+}
+
+/// Role scopes the privileged operations an address can execute
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// GrantRole, RevokeRole, ProposeAdmin, CancelAdminProposal, UpdateConfig,
+    /// UpdateGuardian, SetPaused, ApproveVestingTransfer, SweepSurplus and WriteOffUnbonding
+    Admin,
+    /// RegisterVestingAccount(s) and TopUpVestingAccount
+    Registrar,
+    /// RevokeVestingAccount, DeregisterVestingAccount, PauseVesting,
+    /// ResumeVesting and ForceUndelegate
+    Revoker,
+}
+
+impl Role {
+    pub const ALL: [Role; 3] = [Role::Admin, Role::Registrar, Role::Revoker];
+
+    pub fn key(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Registrar => "registrar",
+            Role::Revoker => "revoker",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct RolesResponse {
+    pub address: String,
+    pub roles: Vec<Role>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct RoleMembersResponse {
+    pub role: Role,
+    pub members: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct PendingAdminsResponse {
+    pub pending_admins: Vec<PendingAdminData>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct PendingAdminData {
+    pub address: String,
+    pub expires: Expiration,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ClaimOperatorsResponse {
+    pub address: String,
+    pub recipient: String,
+    pub operators: Vec<ClaimOperatorData>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ClaimOperatorData {
+    pub operator: String,
+    pub expires: Expiration,
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
@@ -72,23 +426,233 @@ pub struct VestingAccountResponse {
     pub vestings: Vec<VestingData>,
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct VestedAmountAtResponse {
+    pub address: String,
+    pub grant_id: u64,
+    pub time: u64,
+    pub time_unit: TimeUnit,
+    pub vested_amount: Uint128,
+    /// claimable at the time if nothing more is claimed until then
+    pub claimable_amount: Uint128,
+}
+
+/// The vested amount is linear between breakpoints of LinearVesting
+/// and HybridVesting without vesting_interval, otherwise it is constant
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct VestingTimelineResponse {
+    pub address: String,
+    pub grant_id: u64,
+    pub time_unit: TimeUnit,
+    pub breakpoints: Vec<VestingBreakpoint>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct VestingBreakpoint {
+    pub time: u64,
+    pub vested_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ClaimHistoryResponse {
+    pub grant_id: u64,
+    pub claims: Vec<ClaimRecordData>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ClaimRecordData {
+    pub claim_id: u64,
+    pub timestamp: u64,
+    pub height: u64,
+    pub amount: Uint128,
+    pub recipient: String,
+    pub kind: ClaimKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ClaimKind {
+    /// vested amount of the grant
+    #[default]
+    Vested,
+    /// staking rewards of the grant
+    Rewards,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ArchivedVestingAccountsResponse {
+    pub address: String,
+    pub vestings: Vec<ArchivedVestingData>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct ArchivedVestingData {
+    pub grant_id: u64,
+    pub vesting_denom: Denom,
+    pub vesting_amount: Uint128,
+    pub claimed_amount: Uint128,
+    pub vesting_schedule: VestingSchedule,
+    pub time_unit: TimeUnit,
+    pub revoked_at: Option<u64>,
+    pub paused_periods: Vec<PausedPeriod>,
+    pub reason: ArchiveReason,
+    pub archived_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveReason {
+    /// fully claimed by the vesting account
+    Completed,
+    /// revoked after everything vested was claimed
+    Revoked,
+    Deregistered,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct DelegationsResponse {
+    pub address: String,
+    pub grant_id: u64,
+    pub delegations: Vec<DelegationData>,
+    pub unbondings: Vec<Unbonding>,
+    /// including the rewards not yet withdrawn from the validators
+    pub rewards: Uint128,
+    /// delegated tokens lost to slashing, including the slashing not settled yet
+    pub slashed_amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct DelegationData {
+    pub validator: String,
+    pub amount: Uint128,
+}
+
+/// Unbonding is undelegated tokens of a grant, which are not back to the contract yet
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Unbonding {
+    pub amount: Uint128,
+    /// block time in seconds, expected from the configured unbonding period
+    pub completion_time: u64,
+    /// undelegated amount of the denom so far including this unbonding,
+    /// as the unbondings are back to the contract in the order they were undelegated
+    #[serde(default)]
+    pub position: Uint128,
+}
+
+impl Unbonding {
+    /// Back to the contract once the expected time is over
+    /// and the balance has covered every unbonding up to this one
+    pub fn is_returned(&self, time: u64, returned_amount: Uint128) -> bool {
+        self.completion_time <= time && self.position <= returned_amount
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct VotingPowerResponse {
+    pub address: String,
+    pub height: Option<u64>,
+    pub voting_powers: Vec<DenomVotingPower>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct TotalVotingPowerResponse {
+    pub height: Option<u64>,
+    pub voting_powers: Vec<DenomVotingPower>,
+}
+
+/// Amounts of different denoms are not comparable, so the voting power is never summed over them
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct DenomVotingPower {
+    pub denom: Denom,
+    pub voting_power: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct LockedBalanceResponse {
+    pub vesting_denom: Denom,
+    pub deposited_amount: Uint128,
+    pub claimed_amount: Uint128,
+    /// outstanding amount owed to vesting accounts
+    pub locked_amount: Uint128,
+    /// part of the locked amount delegated to validators
+    pub delegated_amount: Uint128,
+    /// part of the locked amount undelegated but not back in the balance yet
+    pub unbonding_amount: Uint128,
+    /// actual balance of the contract
+    pub balance: Uint128,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct AllVestingAccountsResponse {
+    pub vesting_accounts: Vec<VestingAccountData>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
+pub struct VestingAccountData {
+    pub address: String,
+    pub vesting: VestingData,
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, PartialEq, Debug)]
 pub struct VestingData {
+    pub grant_id: u64,
     pub vesting_denom: Denom,
     pub vesting_amount: Uint128,
     pub vested_amount: Uint128,
     pub vesting_schedule: VestingSchedule,
+    pub time_unit: TimeUnit,
+    pub revoked_at: Option<u64>,
+    pub paused_periods: Vec<PausedPeriod>,
     pub claimable_amount: Uint128,
 }
 
+/// TimeUnit selects what the times of a vesting schedule are compared with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeUnit {
+    /// block time in seconds
+    #[default]
+    Seconds,
+    /// block height
+    Height,
+}
+
+impl TimeUnit {
+    /// The current time of the block in this unit
+    pub fn current(&self, block: &BlockInfo) -> u64 {
+        match self {
+            TimeUnit::Seconds => block.time.seconds(),
+            TimeUnit::Height => block.height,
+        }
+    }
+}
+
+/// PausedPeriod is a window in the time unit of a grant, during which nothing is vested
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PausedPeriod {
+    pub start: u64,
+    /// not given while the grant is paused
+    pub end: Option<u64>,
+}
+
+impl PausedPeriod {
+    /// The paused duration before the given time
+    pub fn duration_before(&self, time: u64) -> u64 {
+        let end = self.end.unwrap_or(time).min(time);
+        end.saturating_sub(self.start)
+    }
+}
+
+/// All times are Uint64 seconds, or block heights with TimeUnit::Height, which are encoded as decimal strings
+/// the same as the former String times, so stored schedules are loaded as-is.
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum VestingSchedule {
     /// LinearVesting is used to vest tokens linearly during a time period.
     /// The total_amount will be vested during this period.
     LinearVesting {
-        start_time: String,      // vesting start time in second unit
-        end_time: String,        // vesting end time in second unit
+        start_time: Uint64,      // vesting start time in the grant's time_unit
+        end_time: Uint64,        // vesting end time in the grant's time_unit
         vesting_amount: Uint128, // total vesting amount
     },
     /// PeriodicVesting is used to vest tokens
@@ -97,28 +661,42 @@ pub enum VestingSchedule {
     /// (end_time - start_time) should be multiple of vesting_interval
     /// deposit_amount = amount * ((end_time - start_time) / vesting_interval + 1)
     PeriodicVesting {
-        start_time: String,       // vesting start time in second unit
-        end_time: String,         // vesting end time in second unit
-        vesting_interval: String, // vesting interval in second unit
+        start_time: Uint64,       // vesting start time in the grant's time_unit
+        end_time: Uint64,         // vesting end time in the grant's time_unit
+        vesting_interval: Uint64, // vesting interval in the grant's time_unit
         amount: Uint128,          // the amount will be vested in a interval
     },
     /// CliffVesting is used to vest tokens
     /// according to a predefined schedules vector.
     /// The deposit token must be equal with sum of all schedules.
     CliffVesting { schedules: Vec<CliffSchedule> },
+    /// HybridVesting is used to release cliff_amount at cliff_time
+    /// and vest the rest of tokens from cliff_time to end_time.
+    /// The rest is vested linearly when vesting_interval is not given,
+    /// otherwise it is vested at regular intervals after the cliff.
+    /// To minimize calculation error,
+    /// (end_time - cliff_time) should be multiple of vesting_interval
+    /// deposit_amount = vesting_amount
+    HybridVesting {
+        cliff_time: Uint64,               // cliff release time in the grant's time_unit
+        cliff_amount: Uint128,            // the amount will be released at cliff_time
+        end_time: Uint64,                 // vesting end time in the grant's time_unit
+        vesting_interval: Option<Uint64>, // vesting interval after the cliff in the grant's time_unit
+        vesting_amount: Uint128,          // total vesting amount including cliff_amount
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub struct CliffSchedule {
-    pub release_time: String,
+    pub release_time: Uint64, // release time in the grant's time_unit
     pub release_amount: Uint128,
 }
 
 impl VestingSchedule {
-    pub fn validate(&self, block_time: u64, deposit_amount: Uint128) -> StdResult<()> {
+    pub fn validate(&self, block_time: u64, deposit_amount: Uint128) -> Result<(), ContractError> {
         if deposit_amount.is_zero() {
-            return Err(StdError::generic_err("assert(deposit_amount > 0)"));
+            return Err(ContractError::ZeroDepositAmount {});
         }
 
         match self {
@@ -128,25 +706,29 @@ impl VestingSchedule {
                 vesting_amount,
             } => {
                 if vesting_amount.is_zero() {
-                    return Err(StdError::generic_err("assert(vesting_amount > 0)"));
+                    return Err(ContractError::ZeroVestingAmount {});
                 }
 
-                let start_time = start_time
-                    .parse::<u64>()
-                    .map_err(|_| StdError::generic_err("invalid start_time"))?;
-                let end_time = end_time
-                    .parse::<u64>()
-                    .map_err(|_| StdError::generic_err("invalid end_time"))?;
+                let start_time = start_time.u64();
+                let end_time = end_time.u64();
                 if start_time < block_time {
-                    return Err(StdError::generic_err("assert(start_time >= block_time)"));
+                    return Err(ContractError::TimeBeforeBlockTime {
+                        name: "start_time".to_string(),
+                        time: start_time,
+                        block_time,
+                    });
                 }
                 if end_time < start_time {
-                    return Err(StdError::generic_err("assert(end_time >= start_time)"));
+                    return Err(ContractError::EndTimeBeforeStartTime {
+                        start_time,
+                        end_time,
+                    });
                 }
-                if vesting_amount.u128() != deposit_amount.u128() {
-                    return Err(StdError::generic_err(
-                        "assert(deposit_amount == vesting_amount)",
-                    ));
+                if *vesting_amount != deposit_amount {
+                    return Err(ContractError::DepositAmountMismatch {
+                        deposit_amount,
+                        vesting_amount: *vesting_amount,
+                    });
                 }
             }
             VestingSchedule::PeriodicVesting {
@@ -156,64 +738,123 @@ impl VestingSchedule {
                 amount,
             } => {
                 if amount.is_zero() {
-                    return Err(StdError::generic_err("assert(vesting_amount > 0)"));
+                    return Err(ContractError::ZeroVestingAmount {});
                 }
 
-                let start_time = start_time
-                    .parse::<u64>()
-                    .map_err(|_| StdError::generic_err("invalid start_time"))?;
-                let end_time = end_time
-                    .parse::<u64>()
-                    .map_err(|_| StdError::generic_err("invalid end_time"))?;
-                let vesting_interval = vesting_interval
-                    .parse::<u64>()
-                    .map_err(|_| StdError::generic_err("invalid vesting_interval"))?;
+                let start_time = start_time.u64();
+                let end_time = end_time.u64();
+                let vesting_interval = vesting_interval.u64();
                 if start_time < block_time {
-                    return Err(StdError::generic_err("start_time >= block_time"));
+                    return Err(ContractError::TimeBeforeBlockTime {
+                        name: "start_time".to_string(),
+                        time: start_time,
+                        block_time,
+                    });
                 }
                 if end_time < start_time {
-                    return Err(StdError::generic_err("assert(end_time >= start_time)"));
+                    return Err(ContractError::EndTimeBeforeStartTime {
+                        start_time,
+                        end_time,
+                    });
                 }
                 if vesting_interval == 0 {
-                    return Err(StdError::generic_err("assert(vesting_interval != 0)"));
+                    return Err(ContractError::ZeroVestingInterval {});
                 }
                 let time_period = end_time - start_time;
-                if time_period != (time_period / vesting_interval) * vesting_interval {
-                    return Err(StdError::generic_err(
-                        "assert((end_time - start_time) % vesting_interval == 0)",
-                    ));
+                if time_period % vesting_interval != 0 {
+                    return Err(ContractError::InvalidVestingInterval {
+                        vesting_period: time_period,
+                        vesting_interval,
+                    });
                 }
                 let num_interval = 1 + time_period / vesting_interval;
                 let vesting_amount = amount.checked_mul(Uint128::from(num_interval))?;
                 if vesting_amount != deposit_amount {
-                    return Err(StdError::generic_err(
-                        "assert(deposit_amount = amount * ((end_time - start_time) / vesting_interval + 1))",
-                    ));
+                    return Err(ContractError::DepositAmountMismatch {
+                        deposit_amount,
+                        vesting_amount,
+                    });
                 }
             }
             VestingSchedule::CliffVesting { schedules } => {
-                if schedules.len() == 0 {
-                    return Err(StdError::generic_err("assert(schedules.len() > 0)"));
+                if schedules.is_empty() {
+                    return Err(ContractError::EmptyCliffSchedules {});
                 }
 
                 let mut vesting_amount = Uint128::zero();
                 for schedule in schedules.iter() {
                     if schedule.release_amount.is_zero() {
-                        return Err(StdError::generic_err("assert(release_amount > 0)"));
+                        return Err(ContractError::ZeroReleaseAmount {});
                     }
 
-                    let release_time = schedule.release_time.parse::<u64>().unwrap();
+                    let release_time = schedule.release_time.u64();
                     if release_time < block_time {
-                        return Err(StdError::generic_err("release_time >= block_time"));
+                        return Err(ContractError::TimeBeforeBlockTime {
+                            name: "release_time".to_string(),
+                            time: release_time,
+                            block_time,
+                        });
                     }
 
                     vesting_amount = vesting_amount.checked_add(schedule.release_amount)?;
                 }
 
-                if deposit_amount.u128() != vesting_amount.u128() {
-                    return Err(StdError::generic_err(
-                        "assert(deposit_amount == vesting_amount)",
-                    ));
+                if vesting_amount != deposit_amount {
+                    return Err(ContractError::DepositAmountMismatch {
+                        deposit_amount,
+                        vesting_amount,
+                    });
+                }
+            }
+            VestingSchedule::HybridVesting {
+                cliff_time,
+                cliff_amount,
+                end_time,
+                vesting_interval,
+                vesting_amount,
+            } => {
+                if vesting_amount.is_zero() {
+                    return Err(ContractError::ZeroVestingAmount {});
+                }
+
+                let cliff_time = cliff_time.u64();
+                let end_time = end_time.u64();
+                if cliff_time < block_time {
+                    return Err(ContractError::TimeBeforeBlockTime {
+                        name: "cliff_time".to_string(),
+                        time: cliff_time,
+                        block_time,
+                    });
+                }
+                if end_time < cliff_time {
+                    return Err(ContractError::EndTimeBeforeStartTime {
+                        start_time: cliff_time,
+                        end_time,
+                    });
+                }
+                if cliff_amount > vesting_amount {
+                    return Err(ContractError::InvalidCliffAmount {
+                        cliff_amount: *cliff_amount,
+                        vesting_amount: *vesting_amount,
+                    });
+                }
+                if let Some(vesting_interval) = vesting_interval {
+                    let vesting_interval = vesting_interval.u64();
+                    if vesting_interval == 0 {
+                        return Err(ContractError::ZeroVestingInterval {});
+                    }
+                    if (end_time - cliff_time) % vesting_interval != 0 {
+                        return Err(ContractError::InvalidVestingInterval {
+                            vesting_period: end_time - cliff_time,
+                            vesting_interval,
+                        });
+                    }
+                }
+                if *vesting_amount != deposit_amount {
+                    return Err(ContractError::DepositAmountMismatch {
+                        deposit_amount,
+                        vesting_amount: *vesting_amount,
+                    });
                 }
             }
         }
@@ -221,6 +862,157 @@ impl VestingSchedule {
         Ok(())
     }
 
+    /// The amount of tokens required to be deposited for the schedule
+    pub fn vesting_amount(&self) -> Result<Uint128, ContractError> {
+        match self {
+            VestingSchedule::LinearVesting { vesting_amount, .. } => Ok(*vesting_amount),
+            VestingSchedule::PeriodicVesting {
+                start_time,
+                end_time,
+                vesting_interval,
+                amount,
+            } => {
+                if vesting_interval.is_zero() {
+                    return Err(ContractError::ZeroVestingInterval {});
+                }
+                if end_time < start_time {
+                    return Err(ContractError::EndTimeBeforeStartTime {
+                        start_time: start_time.u64(),
+                        end_time: end_time.u64(),
+                    });
+                }
+
+                let num_interval = 1 + (end_time.u64() - start_time.u64()) / vesting_interval.u64();
+                Ok(amount.checked_mul(Uint128::from(num_interval))?)
+            }
+            VestingSchedule::CliffVesting { schedules } => {
+                let mut vesting_amount = Uint128::zero();
+                for schedule in schedules.iter() {
+                    vesting_amount = vesting_amount.checked_add(schedule.release_amount)?;
+                }
+
+                Ok(vesting_amount)
+            }
+            VestingSchedule::HybridVesting { vesting_amount, .. } => Ok(*vesting_amount),
+        }
+    }
+
+    /// Extend the schedule with `top_up_amount`, so that the amount vested
+    /// at any time is never below the amount vested before the top-up.
+    ///
+    /// * LinearVesting, HybridVesting - add the amount to the `vesting_amount`
+    /// * PeriodicVesting - add intervals after the `end_time`,
+    ///   so the amount must be multiple of the interval `amount`
+    /// * CliffVesting - add the given cliff `schedules`, whose sum must be the amount,
+    ///   released at or after `schedule_time`, the time the schedule is evaluated at
+    pub fn top_up(
+        &self,
+        schedule_time: u64,
+        top_up_amount: Uint128,
+        schedules: Option<Vec<CliffSchedule>>,
+    ) -> Result<VestingSchedule, ContractError> {
+        if top_up_amount.is_zero() {
+            return Err(ContractError::ZeroDepositAmount {});
+        }
+
+        if schedules.is_some() && !matches!(self, VestingSchedule::CliffVesting { .. }) {
+            return Err(ContractError::UnexpectedCliffSchedules {});
+        }
+
+        let mut vesting_schedule = self.clone();
+        match &mut vesting_schedule {
+            VestingSchedule::LinearVesting { vesting_amount, .. }
+            | VestingSchedule::HybridVesting { vesting_amount, .. } => {
+                *vesting_amount = vesting_amount.checked_add(top_up_amount)?;
+            }
+            VestingSchedule::PeriodicVesting {
+                end_time,
+                vesting_interval,
+                amount,
+                ..
+            } => {
+                let invalid_amount = ContractError::InvalidTopUpAmount {
+                    top_up_amount,
+                    interval_amount: *amount,
+                };
+                if top_up_amount.u128().checked_rem(amount.u128()) != Some(0) {
+                    return Err(invalid_amount);
+                }
+
+                let num_interval = u64::try_from(top_up_amount.u128() / amount.u128())
+                    .map_err(|_| invalid_amount)?;
+                *end_time = end_time
+                    .checked_add(Uint64::new(num_interval).checked_mul(*vesting_interval)?)?;
+            }
+            VestingSchedule::CliffVesting {
+                schedules: cliff_schedules,
+            } => {
+                let schedules = schedules.unwrap_or_default();
+
+                // validate the new entries same as registering them
+                VestingSchedule::CliffVesting {
+                    schedules: schedules.clone(),
+                }
+                .validate(schedule_time, top_up_amount)?;
+
+                cliff_schedules.extend(schedules);
+            }
+        }
+
+        Ok(vesting_schedule)
+    }
+
+    /// Times after start_after in ascending order, where the vested amount
+    /// changes or starts changing, at most limit
+    pub fn breakpoints(&self, start_after: Option<u64>, limit: usize) -> Vec<u64> {
+        let mut times = match self {
+            VestingSchedule::LinearVesting {
+                start_time,
+                end_time,
+                ..
+            } => vec![start_time.u64(), end_time.u64()],
+            VestingSchedule::PeriodicVesting {
+                start_time,
+                end_time,
+                vesting_interval,
+                ..
+            } => interval_times(
+                start_time.u64(),
+                end_time.u64(),
+                vesting_interval.u64(),
+                start_after,
+                limit,
+            ),
+            VestingSchedule::CliffVesting { schedules } => {
+                let mut times: Vec<u64> = schedules.iter().map(|s| s.release_time.u64()).collect();
+                times.sort_unstable();
+                times
+            }
+            VestingSchedule::HybridVesting {
+                cliff_time,
+                end_time,
+                vesting_interval,
+                ..
+            } => match vesting_interval {
+                Some(vesting_interval) => interval_times(
+                    cliff_time.u64(),
+                    end_time.u64(),
+                    vesting_interval.u64(),
+                    start_after,
+                    limit,
+                ),
+                None => vec![cliff_time.u64(), end_time.u64()],
+            },
+        };
+
+        if let Some(start_after) = start_after {
+            times.retain(|time| *time > start_after);
+        }
+        times.dedup();
+        times.truncate(limit);
+        times
+    }
+
     pub fn vested_amount(&self, block_time: u64) -> StdResult<Uint128> {
         match self {
             VestingSchedule::LinearVesting {
@@ -228,8 +1020,8 @@ impl VestingSchedule {
                 end_time,
                 vesting_amount,
             } => {
-                let start_time = start_time.parse::<u64>().unwrap();
-                let end_time = end_time.parse::<u64>().unwrap();
+                let start_time = start_time.u64();
+                let end_time = end_time.u64();
 
                 if block_time <= start_time {
                     return Ok(Uint128::zero());
@@ -251,9 +1043,9 @@ impl VestingSchedule {
                 vesting_interval,
                 amount,
             } => {
-                let start_time = start_time.parse::<u64>().unwrap();
-                let end_time = end_time.parse::<u64>().unwrap();
-                let vesting_interval = vesting_interval.parse::<u64>().unwrap();
+                let start_time = start_time.u64();
+                let end_time = end_time.u64();
+                let vesting_interval = vesting_interval.u64();
 
                 if block_time < start_time {
                     return Ok(Uint128::zero());
@@ -271,7 +1063,7 @@ impl VestingSchedule {
                 schedules
                     .iter()
                     .map(|s| {
-                        let release_time = s.release_time.parse::<u64>().unwrap();
+                        let release_time = s.release_time.u64();
                         if block_time >= release_time {
                             s.release_amount.u128()
                         } else {
@@ -280,15 +1072,79 @@ impl VestingSchedule {
                     })
                     .sum(),
             )),
+            VestingSchedule::HybridVesting {
+                cliff_time,
+                cliff_amount,
+                end_time,
+                vesting_interval,
+                vesting_amount,
+            } => {
+                let cliff_time = cliff_time.u64();
+                let end_time = end_time.u64();
+
+                if block_time < cliff_time {
+                    return Ok(Uint128::zero());
+                }
+
+                if block_time >= end_time {
+                    return Ok(*vesting_amount);
+                }
+
+                // the rest amount is vested after the cliff
+                let tail_amount = vesting_amount.checked_sub(*cliff_amount)?;
+                let (passed, total) = match vesting_interval {
+                    Some(vesting_interval) => {
+                        let vesting_interval = vesting_interval.u64();
+                        (
+                            (block_time - cliff_time) / vesting_interval,
+                            (end_time - cliff_time) / vesting_interval,
+                        )
+                    }
+                    None => (block_time - cliff_time, end_time - cliff_time),
+                };
+
+                let vested_tail = tail_amount
+                    .checked_mul(Uint128::from(passed))?
+                    .checked_div(Uint128::from(total))?;
+
+                Ok(cliff_amount.checked_add(vested_tail)?)
+            }
         }
     }
 }
 
+/// Interval boundaries from start_time up to end_time, starting after start_after
+fn interval_times(
+    start_time: u64,
+    end_time: u64,
+    vesting_interval: u64,
+    start_after: Option<u64>,
+    limit: usize,
+) -> Vec<u64> {
+    let first = match start_after {
+        Some(start_after) if start_after >= start_time => {
+            ((start_after - start_time) / vesting_interval).saturating_add(1)
+        }
+        _ => 0,
+    };
+
+    // the boundaries stop at end_time, or where they overflow before reaching it
+    (first..=u64::MAX)
+        .map_while(|i| {
+            i.checked_mul(vesting_interval)
+                .and_then(|offset| start_time.checked_add(offset))
+        })
+        .take_while(|time| *time <= end_time)
+        .take(limit)
+        .chain(std::iter::once(end_time))
+        .collect()
+}
+
 #[test]
 fn linear_vesting_vested_amount() {
     let schedule = VestingSchedule::LinearVesting {
-        start_time: "100".to_string(),
-        end_time: "110".to_string(),
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
         vesting_amount: Uint128::new(1000000u128),
     };
 
@@ -310,9 +1166,9 @@ fn linear_vesting_vested_amount() {
 #[test]
 fn periodic_vesting_vested_amount() {
     let schedule = VestingSchedule::PeriodicVesting {
-        start_time: "105".to_string(),
-        end_time: "110".to_string(),
-        vesting_interval: "5".to_string(),
+        start_time: Uint64::new(105),
+        end_time: Uint64::new(110),
+        vesting_interval: Uint64::new(5),
         amount: Uint128::new(500000u128),
     };
 
@@ -336,11 +1192,11 @@ fn cliff_vesting_vested_amount() {
     let schedule = VestingSchedule::CliffVesting {
         schedules: vec![
             CliffSchedule {
-                release_time: "105".to_string(),
+                release_time: Uint64::new(105),
                 release_amount: Uint128::new(500000u128),
             },
             CliffSchedule {
-                release_time: "110".to_string(),
+                release_time: Uint64::new(110),
                 release_amount: Uint128::new(500000u128),
             },
         ],
@@ -360,3 +1216,289 @@ fn cliff_vesting_vested_amount() {
         Uint128::new(1000000u128)
     );
 }
+
+#[test]
+fn hybrid_vesting_vested_amount() {
+    let schedule = VestingSchedule::HybridVesting {
+        cliff_time: Uint64::new(105),
+        cliff_amount: Uint128::new(250000u128),
+        end_time: Uint64::new(115),
+        vesting_interval: None,
+        vesting_amount: Uint128::new(1000000u128),
+    };
+
+    assert_eq!(schedule.vested_amount(100).unwrap(), Uint128::zero());
+    assert_eq!(
+        schedule.vested_amount(105).unwrap(),
+        Uint128::new(250000u128)
+    );
+    assert_eq!(
+        schedule.vested_amount(111).unwrap(),
+        Uint128::new(700000u128)
+    );
+    assert_eq!(
+        schedule.vested_amount(115).unwrap(),
+        Uint128::new(1000000u128)
+    );
+    assert_eq!(
+        schedule.vested_amount(120).unwrap(),
+        Uint128::new(1000000u128)
+    );
+
+    let schedule = VestingSchedule::HybridVesting {
+        cliff_time: Uint64::new(105),
+        cliff_amount: Uint128::new(250000u128),
+        end_time: Uint64::new(115),
+        vesting_interval: Some(Uint64::new(5)),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+
+    assert_eq!(schedule.vested_amount(100).unwrap(), Uint128::zero());
+    assert_eq!(
+        schedule.vested_amount(105).unwrap(),
+        Uint128::new(250000u128)
+    );
+    assert_eq!(
+        schedule.vested_amount(109).unwrap(),
+        Uint128::new(250000u128)
+    );
+    assert_eq!(
+        schedule.vested_amount(110).unwrap(),
+        Uint128::new(625000u128)
+    );
+    assert_eq!(
+        schedule.vested_amount(115).unwrap(),
+        Uint128::new(1000000u128)
+    );
+}
+
+#[test]
+fn top_up_vesting_schedule() {
+    let schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    let top_up = schedule
+        .top_up(105, Uint128::new(500000u128), None)
+        .unwrap();
+    assert_eq!(
+        top_up,
+        VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1500000u128),
+        }
+    );
+    assert!(top_up.vested_amount(105).unwrap() >= schedule.vested_amount(105).unwrap());
+
+    match schedule.top_up(105, Uint128::zero(), None).unwrap_err() {
+        ContractError::ZeroDepositAmount {} => {}
+        _ => panic!("should not enter"),
+    }
+    match schedule
+        .top_up(105, Uint128::new(500000u128), Some(vec![]))
+        .unwrap_err()
+    {
+        ContractError::UnexpectedCliffSchedules {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let schedule = VestingSchedule::PeriodicVesting {
+        start_time: Uint64::new(105),
+        end_time: Uint64::new(110),
+        vesting_interval: Uint64::new(5),
+        amount: Uint128::new(500000u128),
+    };
+    assert_eq!(
+        schedule
+            .top_up(105, Uint128::new(1000000u128), None)
+            .unwrap(),
+        VestingSchedule::PeriodicVesting {
+            start_time: Uint64::new(105),
+            end_time: Uint64::new(120),
+            vesting_interval: Uint64::new(5),
+            amount: Uint128::new(500000u128),
+        }
+    );
+    match schedule
+        .top_up(105, Uint128::new(700000u128), None)
+        .unwrap_err()
+    {
+        ContractError::InvalidTopUpAmount {
+            top_up_amount,
+            interval_amount,
+        } => {
+            assert_eq!(top_up_amount, Uint128::new(700000u128));
+            assert_eq!(interval_amount, Uint128::new(500000u128));
+        }
+        _ => panic!("should not enter"),
+    }
+
+    let schedule = VestingSchedule::CliffVesting {
+        schedules: vec![CliffSchedule {
+            release_time: Uint64::new(105),
+            release_amount: Uint128::new(500000u128),
+        }],
+    };
+    assert_eq!(
+        schedule
+            .top_up(
+                106,
+                Uint128::new(300000u128),
+                Some(vec![CliffSchedule {
+                    release_time: Uint64::new(110),
+                    release_amount: Uint128::new(300000u128),
+                }]),
+            )
+            .unwrap(),
+        VestingSchedule::CliffVesting {
+            schedules: vec![
+                CliffSchedule {
+                    release_time: Uint64::new(105),
+                    release_amount: Uint128::new(500000u128),
+                },
+                CliffSchedule {
+                    release_time: Uint64::new(110),
+                    release_amount: Uint128::new(300000u128),
+                },
+            ],
+        }
+    );
+    match schedule
+        .top_up(106, Uint128::new(300000u128), None)
+        .unwrap_err()
+    {
+        ContractError::EmptyCliffSchedules {} => {}
+        _ => panic!("should not enter"),
+    }
+    match schedule
+        .top_up(
+            106,
+            Uint128::new(300000u128),
+            Some(vec![CliffSchedule {
+                release_time: Uint64::new(105),
+                release_amount: Uint128::new(300000u128),
+            }]),
+        )
+        .unwrap_err()
+    {
+        ContractError::TimeBeforeBlockTime { name, .. } => assert_eq!(name, "release_time"),
+        _ => panic!("should not enter"),
+    }
+
+    let schedule = VestingSchedule::HybridVesting {
+        cliff_time: Uint64::new(100),
+        cliff_amount: Uint128::new(200000u128),
+        end_time: Uint64::new(110),
+        vesting_interval: None,
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    assert_eq!(
+        schedule
+            .top_up(105, Uint128::new(400000u128), None)
+            .unwrap(),
+        VestingSchedule::HybridVesting {
+            cliff_time: Uint64::new(100),
+            cliff_amount: Uint128::new(200000u128),
+            end_time: Uint64::new(110),
+            vesting_interval: None,
+            vesting_amount: Uint128::new(1400000u128),
+        }
+    );
+}
+
+#[test]
+fn legacy_string_times_compatibility() {
+    let schedule: VestingSchedule = cosmwasm_std::from_slice(
+        br#"{"cliff_vesting":{"schedules":[{"release_time":"105","release_amount":"500000"}]}}"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        schedule,
+        VestingSchedule::CliffVesting {
+            schedules: vec![CliffSchedule {
+                release_time: Uint64::new(105),
+                release_amount: Uint128::new(500000u128),
+            }],
+        }
+    );
+    assert_eq!(
+        cosmwasm_std::to_vec(&schedule).unwrap(),
+        br#"{"cliff_vesting":{"schedules":[{"release_time":"105","release_amount":"500000"}]}}"#
+    );
+}
+
+#[test]
+fn vesting_schedule_breakpoints() {
+    let schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    assert_eq!(schedule.breakpoints(None, 10), vec![100, 110]);
+    assert_eq!(schedule.breakpoints(Some(100), 10), vec![110]);
+
+    // the last interval ends at end_time
+    let schedule = VestingSchedule::PeriodicVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(125),
+        vesting_interval: Uint64::new(10),
+        amount: Uint128::new(100000u128),
+    };
+    assert_eq!(schedule.breakpoints(None, 10), vec![100, 110, 120, 125]);
+    assert_eq!(schedule.breakpoints(None, 2), vec![100, 110]);
+    assert_eq!(schedule.breakpoints(Some(105), 2), vec![110, 120]);
+    assert_eq!(schedule.breakpoints(Some(120), 10), vec![125]);
+    assert_eq!(schedule.breakpoints(Some(125), 10), Vec::<u64>::new());
+
+    // intervals near the end of the time range do not overflow
+    let schedule = VestingSchedule::PeriodicVesting {
+        start_time: Uint64::new(u64::MAX - 15),
+        end_time: Uint64::new(u64::MAX),
+        vesting_interval: Uint64::new(10),
+        amount: Uint128::new(100000u128),
+    };
+    assert_eq!(
+        schedule.breakpoints(None, 10),
+        vec![u64::MAX - 15, u64::MAX - 5, u64::MAX]
+    );
+    let schedule = VestingSchedule::PeriodicVesting {
+        start_time: Uint64::zero(),
+        end_time: Uint64::new(u64::MAX),
+        vesting_interval: Uint64::new(1),
+        amount: Uint128::new(100000u128),
+    };
+    assert_eq!(
+        schedule.breakpoints(Some(u64::MAX - 2), 10),
+        vec![u64::MAX - 1, u64::MAX]
+    );
+
+    let schedule = VestingSchedule::CliffVesting {
+        schedules: vec![
+            CliffSchedule {
+                release_time: Uint64::new(110),
+                release_amount: Uint128::new(500000u128),
+            },
+            CliffSchedule {
+                release_time: Uint64::new(105),
+                release_amount: Uint128::new(250000u128),
+            },
+            CliffSchedule {
+                release_time: Uint64::new(110),
+                release_amount: Uint128::new(250000u128),
+            },
+        ],
+    };
+    assert_eq!(schedule.breakpoints(None, 10), vec![105, 110]);
+
+    let schedule = VestingSchedule::HybridVesting {
+        cliff_time: Uint64::new(100),
+        cliff_amount: Uint128::new(200000u128),
+        end_time: Uint64::new(130),
+        vesting_interval: Some(Uint64::new(10)),
+        vesting_amount: Uint128::new(1100000u128),
+    };
+    assert_eq!(schedule.breakpoints(Some(100), 10), vec![110, 120, 130]);
+}
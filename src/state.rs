@@ -1,26 +1,565 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::msg::VestingSchedule;
-use cosmwasm_std::Uint128;
-use cw20::Denom;
-use cw_storage_plus::{Item, Map};
+use crate::msg::{
+    ArchiveReason, ClaimKind, PausedOperations, PausedPeriod, Role, TimeUnit, Unbonding,
+    VestingSchedule,
+};
+use cosmwasm_std::{
+    Addr, BlockInfo, Decimal, Empty, Fraction, StdError, StdResult, Storage, Uint128,
+};
+use cw20::{Denom, Expiration};
+use cw_storage_plus::{
+    Index, IndexList, IndexedMap, Item, Map, MultiIndex, SnapshotMap, Strategy, U64Key,
+};
 
-pub const MASTER_ADDRESS: Item<String> = Item::new("master_address");
-pub const VESTING_ACCOUNTS: Map<(&str, &str), VestingAccount> = Map::new("vesting_accounts");
+pub const CONFIG: Item<Config> = Item::new("config");
+/// Addresses holding the roles, keyed by (role key, address)
+pub const ROLES: Map<(&str, &Addr), Empty> = Map::new("roles");
+/// Expiration of the admin proposals, which the proposed addresses have to accept
+pub const PENDING_ADMINS: Map<&Addr, Expiration> = Map::new("pending_admins");
+/// Storage layout version, which selects the steps run by the migration
+pub const LAYOUT_VERSION: Item<u64> = Item::new("layout_version");
+pub const CURRENT_LAYOUT_VERSION: u64 = 5;
+pub const LAST_GRANT_ID: Item<u64> = Item::new("last_grant_id");
+/// Transfers waiting for an admin approval, keyed by grant_id
+pub const PENDING_TRANSFERS: Map<U64Key, PendingTransfer> = Map::new("pending_transfers");
+/// Expiration of the claim operators, keyed by (beneficiary, operator)
+pub const CLAIM_OPERATORS: Map<(&Addr, &Addr), Expiration> = Map::new("claim_operators");
+/// Recipients of the claimed tokens, which beneficiaries registered in place of themselves
+pub const CLAIM_RECIPIENTS: Map<&Addr, Addr> = Map::new("claim_recipients");
+/// Running totals per denom_key, to reconcile the contract balance against grants
+pub const TOTAL_LOCKED: Map<&str, LockedBalance> = Map::new("total_locked");
+pub const LAST_CLAIM_ID: Item<u64> = Item::new("last_claim_id");
+/// Claims of every grant, keyed by (grant_id, claim_id)
+pub const CLAIM_HISTORY: Map<(U64Key, U64Key), ClaimRecord> = Map::new("claim_history");
+/// Grants removed after being fully claimed, revoked or deregistered, keyed by (address, grant_id)
+pub const ARCHIVED_ACCOUNTS: Map<(&Addr, U64Key), ArchivedAccount> = Map::new("archived_accounts");
+/// Delegations of the grants, keyed by (grant_id, validator)
+pub const GRANT_DELEGATIONS: Map<(U64Key, &str), StakingPosition> = Map::new("grant_delegations");
+/// Delegations of all grants, keyed by validator
+pub const VALIDATOR_DELEGATIONS: Map<&str, StakingPosition> = Map::new("validator_delegations");
+/// Vesting amount minus claimed amount of the grants, keyed by (address, denom_key)
+/// and snapshotted at every block height
+pub const VOTING_POWER: SnapshotMap<(&Addr, &str), Uint128> = SnapshotMap::new(
+    "voting_power",
+    "voting_power__checkpoints",
+    "voting_power__changelog",
+    Strategy::EveryBlock,
+);
+/// Voting power of all addresses, keyed by denom_key
+pub const TOTAL_VOTING_POWER: SnapshotMap<&str, Uint128> = SnapshotMap::new(
+    "total_voting_power",
+    "total_voting_power__checkpoints",
+    "total_voting_power__changelog",
+    Strategy::EveryBlock,
+);
+
+pub struct VestingAccountIndexes<'a> {
+    /// (denom_key, pk) -> pk, to list the grants of a denom
+    pub denom: MultiIndex<'a, (Vec<u8>, Vec<u8>), VestingAccount>,
+}
+
+impl<'a> IndexList<VestingAccount> for VestingAccountIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<VestingAccount>> + '_> {
+        let v: Vec<&dyn Index<VestingAccount>> = vec![&self.denom];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Vesting accounts keyed by (address, grant_id) and indexed by denom
+pub fn vesting_accounts<'a>(
+) -> IndexedMap<'a, (&'a Addr, U64Key), VestingAccount, VestingAccountIndexes<'a>> {
+    let indexes = VestingAccountIndexes {
+        denom: MultiIndex::new(
+            |account, pk| (denom_to_key(account.vesting_denom.clone()).into_bytes(), pk),
+            "vesting_accounts",
+            "vesting_accounts__denom",
+        ),
+    };
+    IndexedMap::new("vesting_accounts", indexes)
+}
+
+/// Contracts instantiated before the config was added load the default
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct Config {
+    pub require_transfer_approval: bool,
+    /// seconds until undelegated tokens are back to the contract
+    #[serde(default = "default_unbonding_period")]
+    pub unbonding_period: u64,
+    #[serde(default)]
+    pub guardian: Option<Addr>,
+    #[serde(default)]
+    pub paused: PausedOperations,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            require_transfer_approval: false,
+            unbonding_period: default_unbonding_period(),
+            guardian: None,
+            paused: PausedOperations::default(),
+        }
+    }
+}
+
+fn default_unbonding_period() -> u64 {
+    // 21 days
+    21 * 24 * 60 * 60
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct PendingTransfer {
+    pub address: Addr,
+    pub new_address: Addr,
+}
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 pub struct VestingAccount {
-    pub address: String,
+    pub address: Addr,
+    pub grant_id: u64,
     pub vesting_denom: Denom,
     pub vesting_amount: Uint128,
     pub vesting_schedule: VestingSchedule,
     pub claimed_amount: Uint128,
+    /// grants stored before time units were added are in seconds
+    #[serde(default)]
+    pub time_unit: TimeUnit,
+    /// time of the revocation in the time_unit, after which nothing more is vested
+    #[serde(default)]
+    pub revoked_at: Option<u64>,
+    /// windows excluded from the vesting clock, the last one is open while paused
+    #[serde(default)]
+    pub paused_periods: Vec<PausedPeriod>,
+    /// tokens of the grant delegated to validators
+    #[serde(default)]
+    pub delegated_amount: Uint128,
+    /// undelegated tokens on the way back to the contract
+    #[serde(default)]
+    pub unbondings: Vec<Unbonding>,
+    /// staking rewards owed to the vesting account
+    #[serde(default)]
+    pub rewards: Uint128,
+    /// delegated tokens lost to slashing, which are never paid out
+    #[serde(default)]
+    pub slashed_amount: Uint128,
+}
+
+impl VestingAccount {
+    pub fn vested_amount(&self, block: &BlockInfo) -> StdResult<Uint128> {
+        self.vested_amount_at(self.time_unit.current(block))
+    }
+
+    /// Vested amount at the given time, an ongoing pause is treated as never resumed
+    pub fn vested_amount_at(&self, time: u64) -> StdResult<Uint128> {
+        let time = match self.revoked_at {
+            Some(revoked_at) => time.min(revoked_at),
+            None => time,
+        };
+
+        self.vesting_schedule
+            .vested_amount(self.schedule_time(time))
+    }
+
+    /// Times after start_after where the vested amount changes or starts changing,
+    /// including the boundaries of paused periods and the revocation
+    pub fn breakpoints(&self, start_after: Option<u64>, limit: usize) -> Vec<u64> {
+        let mut times: Vec<u64> = self
+            .vesting_schedule
+            .breakpoints(start_after.map(|time| self.schedule_time(time)), limit)
+            .into_iter()
+            .filter_map(|time| self.postponed_time(time))
+            .collect();
+
+        for period in self.paused_periods.iter() {
+            times.push(period.start);
+            times.extend(period.end);
+        }
+        if let Some(revoked_at) = self.revoked_at {
+            times.push(revoked_at);
+            times.retain(|time| *time <= revoked_at);
+        }
+
+        if let Some(start_after) = start_after {
+            times.retain(|time| *time > start_after);
+        }
+        times.sort_unstable();
+        times.dedup();
+        times.truncate(limit);
+        times
+    }
+
+    /// The time the schedule is evaluated at, postponed by the paused duration
+    pub fn schedule_time(&self, time: u64) -> u64 {
+        let paused_duration: u64 = self
+            .paused_periods
+            .iter()
+            .map(|period| period.duration_before(time))
+            .sum();
+
+        time - paused_duration
+    }
+
+    // the time the schedule reaches the given schedule time, never during an ongoing pause
+    fn postponed_time(&self, schedule_time: u64) -> Option<u64> {
+        let mut time = schedule_time;
+        for period in self.paused_periods.iter() {
+            if period.start >= time {
+                break;
+            }
+            time += period.end? - period.start;
+        }
+
+        Some(time)
+    }
+
+    /// Delegated and unbonding tokens, which are not in the contract,
+    /// given the undelegated amount of the denom returned to the contract so far
+    pub fn staked_amount(&self, block: &BlockInfo, returned_amount: Uint128) -> Uint128 {
+        self.unbondings
+            .iter()
+            .filter(|unbonding| !unbonding.is_returned(block.time.seconds(), returned_amount))
+            .map(|unbonding| unbonding.amount)
+            .fold(self.delegated_amount, |total, amount| total + amount)
+    }
+
+    /// Tokens of the grant held by the contract, which can be paid out now
+    pub fn liquid_amount(&self, block: &BlockInfo, returned_amount: Uint128) -> StdResult<Uint128> {
+        Ok(self
+            .remaining_amount()?
+            .checked_sub(self.staked_amount(block, returned_amount))?)
+    }
+
+    /// Drop the unbondings back to the contract
+    pub fn settle_unbondings(&mut self, block: &BlockInfo, returned_amount: Uint128) {
+        self.unbondings
+            .retain(|unbonding| !unbonding.is_returned(block.time.seconds(), returned_amount));
+    }
+
+    /// Tokens of the grant still owed to the vesting account apart from the rewards
+    pub fn remaining_amount(&self) -> StdResult<Uint128> {
+        Ok(self
+            .vesting_amount
+            .checked_sub(self.claimed_amount)?
+            .checked_sub(self.slashed_amount)?)
+    }
+
+    /// Nothing is left to be paid out
+    pub fn is_completed(&self) -> bool {
+        self.claimed_amount + self.slashed_amount == self.vesting_amount && self.rewards.is_zero()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        matches!(
+            self.paused_periods.last(),
+            Some(PausedPeriod { end: None, .. })
+        )
+    }
+}
+
+/// Delegated amount with the rewards per delegated token
+/// and the tokens left per delegated token after slashing, at the last settlement
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct StakingPosition {
+    pub amount: Uint128,
+    pub reward_index: Decimal,
+    #[serde(default = "Decimal::one")]
+    pub slash_factor: Decimal,
+}
+
+impl Default for StakingPosition {
+    fn default() -> Self {
+        StakingPosition {
+            amount: Uint128::zero(),
+            reward_index: Decimal::zero(),
+            slash_factor: Decimal::one(),
+        }
+    }
+}
+
+impl StakingPosition {
+    /// Tokens left of a grant position after the slashing of the validator since its last settlement
+    pub fn unslashed_amount(&self, validator_position: &StakingPosition) -> Uint128 {
+        if self.slash_factor.is_zero() {
+            return Uint128::zero();
+        }
+
+        self.amount.multiply_ratio(
+            validator_position.slash_factor.numerator(),
+            self.slash_factor.numerator(),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ClaimRecord {
+    /// block time in seconds
+    pub timestamp: u64,
+    pub height: u64,
+    pub amount: Uint128,
+    pub recipient: Addr,
+    /// records before the rewards were recorded are vested amounts
+    #[serde(default)]
+    pub kind: ClaimKind,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct ArchivedAccount {
+    /// the grant as it was removed, claimed_amount includes the final payout
+    pub account: VestingAccount,
+    pub reason: ArchiveReason,
+    /// block time in seconds
+    pub archived_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+pub struct LockedBalance {
+    pub vesting_denom: Denom,
+    /// sum of all deposits backing grants
+    pub deposited_amount: Uint128,
+    /// sum of all amounts sent to vesting accounts
+    pub claimed_amount: Uint128,
+    /// amount still owed to vesting accounts
+    pub locked_amount: Uint128,
+    /// tokens delegated to validators, which are owed but not in the balance
+    #[serde(default)]
+    pub delegated_amount: Uint128,
+    /// undelegated tokens not seen back in the balance yet
+    #[serde(default)]
+    pub unbonding_amount: Uint128,
+    /// all tokens undelegated so far, which are back in the order they were undelegated
+    #[serde(default)]
+    pub undelegated_amount: Uint128,
+}
+
+impl LockedBalance {
+    pub fn new(vesting_denom: Denom) -> Self {
+        LockedBalance {
+            vesting_denom,
+            deposited_amount: Uint128::zero(),
+            claimed_amount: Uint128::zero(),
+            locked_amount: Uint128::zero(),
+            delegated_amount: Uint128::zero(),
+            unbonding_amount: Uint128::zero(),
+            undelegated_amount: Uint128::zero(),
+        }
+    }
+
+    /// Undelegated tokens back in the balance or written off so far
+    pub fn total_returned(&self) -> Uint128 {
+        self.undelegated_amount
+            .saturating_sub(self.unbonding_amount)
+    }
+
+    /// Unbonding tokens which are back in the given balance.
+    /// The balance over the amount not away for staking is taken as returned first,
+    /// so that the unbonding tokens are never counted twice.
+    pub fn returned_amount(&self, balance: Uint128) -> Uint128 {
+        (balance + self.delegated_amount + self.unbonding_amount)
+            .saturating_sub(self.locked_amount)
+            .min(self.unbonding_amount)
+    }
+
+    /// Take the unbonding tokens back in the given balance out of the unbonding amount
+    pub fn settle_unbonding(&mut self, balance: Uint128) {
+        self.unbonding_amount -= self.returned_amount(balance);
+    }
+
+    /// The given balance over the amount owed, counting the tokens away for staking
+    pub fn surplus_amount(&self, balance: Uint128) -> Uint128 {
+        (balance + self.delegated_amount + self.unbonding_amount).saturating_sub(self.locked_amount)
+    }
+}
+
+pub fn load_config(storage: &dyn Storage) -> StdResult<Config> {
+    Ok(CONFIG.may_load(storage)?.unwrap_or_default())
+}
+
+pub fn has_role(storage: &dyn Storage, role: Role, address: &Addr) -> bool {
+    ROLES.has(storage, (role.key(), address))
+}
+
+/// Record a deposit backing a new grant
+pub fn lock_amount(storage: &mut dyn Storage, denom: &Denom, amount: Uint128) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED
+        .may_load(storage, &denom_key)?
+        .unwrap_or_else(|| LockedBalance::new(denom.clone()));
+    locked.deposited_amount = locked.deposited_amount.checked_add(amount)?;
+    locked.locked_amount = locked.locked_amount.checked_add(amount)?;
+    TOTAL_LOCKED.save(storage, &denom_key, &locked)
+}
+
+/// Record tokens delegated out of the balance
+pub fn delegate_amount(storage: &mut dyn Storage, denom: &Denom, amount: Uint128) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED.load(storage, &denom_key)?;
+    locked.delegated_amount = locked.delegated_amount.checked_add(amount)?;
+    TOTAL_LOCKED.save(storage, &denom_key, &locked)
+}
+
+/// Record delegated tokens on the way back to the balance,
+/// returning the undelegated amount of the denom so far
+pub fn undelegate_amount(
+    storage: &mut dyn Storage,
+    denom: &Denom,
+    amount: Uint128,
+) -> StdResult<Uint128> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED.load(storage, &denom_key)?;
+    locked.delegated_amount = locked.delegated_amount.checked_sub(amount)?;
+    locked.unbonding_amount = locked.unbonding_amount.checked_add(amount)?;
+    locked.undelegated_amount = locked.undelegated_amount.checked_add(amount)?;
+    TOTAL_LOCKED.save(storage, &denom_key, &locked)?;
+    Ok(locked.undelegated_amount)
+}
+
+/// Record delegated tokens lost to slashing, which are no longer owed
+pub fn slash_amount(storage: &mut dyn Storage, denom: &Denom, amount: Uint128) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED.load(storage, &denom_key)?;
+    locked.delegated_amount = locked.delegated_amount.checked_sub(amount)?;
+    locked.locked_amount = locked.locked_amount.checked_sub(amount)?;
+    TOTAL_LOCKED.save(storage, &denom_key, &locked)
+}
+
+/// Record unbonding tokens lost to slashing, which are no longer owed.
+/// They are taken as returned, so the later unbondings are back once the balance covers them.
+pub fn write_off_amount(
+    storage: &mut dyn Storage,
+    denom: &Denom,
+    amount: Uint128,
+) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED.load(storage, &denom_key)?;
+    locked.unbonding_amount = locked.unbonding_amount.checked_sub(amount)?;
+    locked.locked_amount = locked.locked_amount.checked_sub(amount)?;
+    TOTAL_LOCKED.save(storage, &denom_key, &locked)
+}
+
+/// Record the release of `claimed_amount` to vesting accounts
+/// and of `returned_amount` back to the revoker
+pub fn unlock_amount(
+    storage: &mut dyn Storage,
+    denom: &Denom,
+    claimed_amount: Uint128,
+    returned_amount: Uint128,
+) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    let mut locked = TOTAL_LOCKED.load(storage, &denom_key)?;
+    locked.claimed_amount = locked.claimed_amount.checked_add(claimed_amount)?;
+    locked.locked_amount = locked
+        .locked_amount
+        .checked_sub(claimed_amount.checked_add(returned_amount)?)?;
+    TOTAL_LOCKED.save(storage, &denom_key, &locked)
+}
+
+/// Record the voting power gained by a deposit or a transferred grant
+pub fn add_voting_power(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    denom: &Denom,
+    amount: Uint128,
+) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    VOTING_POWER.update(storage, (address, &denom_key), height, |power| {
+        StdResult::Ok(power.unwrap_or_default().checked_add(amount)?)
+    })?;
+    TOTAL_VOTING_POWER.update(storage, &denom_key, height, |power| {
+        StdResult::Ok(power.unwrap_or_default().checked_add(amount)?)
+    })?;
+    Ok(())
+}
+
+/// Record the voting power lost by a claim, a returned amount or a transferred grant.
+/// The entries are kept at zero, so that the denoms held in the past can be listed.
+pub fn sub_voting_power(
+    storage: &mut dyn Storage,
+    height: u64,
+    address: &Addr,
+    denom: &Denom,
+    amount: Uint128,
+) -> StdResult<()> {
+    let denom_key = denom_to_key(denom.clone());
+    VOTING_POWER.update(storage, (address, &denom_key), height, |power| {
+        StdResult::Ok(power.unwrap_or_default().checked_sub(amount)?)
+    })?;
+    TOTAL_VOTING_POWER.update(storage, &denom_key, height, |power| {
+        StdResult::Ok(power.unwrap_or_default().checked_sub(amount)?)
+    })?;
+    Ok(())
 }
 
 pub fn denom_to_key(denom: Denom) -> String {
     match denom {
-        Denom::Cw20(addr) => format!("cw20-{}", addr.to_string()),
+        Denom::Cw20(addr) => format!("cw20-{}", addr),
         Denom::Native(denom) => format!("native-{}", denom),
     }
 }
+
+pub fn key_to_denom(denom_key: &str) -> StdResult<Denom> {
+    if let Some(addr) = denom_key.strip_prefix("cw20-") {
+        Ok(Denom::Cw20(Addr::unchecked(addr)))
+    } else if let Some(denom) = denom_key.strip_prefix("native-") {
+        Ok(Denom::Native(denom.to_string()))
+    } else {
+        Err(StdError::generic_err(format!(
+            "Invalid denom key: {}",
+            denom_key
+        )))
+    }
+}
+
+/// Issue a new grant id, which is unique over all vesting accounts
+pub fn next_grant_id(storage: &mut dyn Storage) -> StdResult<u64> {
+    let grant_id = LAST_GRANT_ID.may_load(storage)?.unwrap_or_default() + 1;
+    LAST_GRANT_ID.save(storage, &grant_id)?;
+    Ok(grant_id)
+}
+
+pub fn record_claim(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    grant_id: u64,
+    amount: Uint128,
+    recipient: &Addr,
+    kind: ClaimKind,
+) -> StdResult<()> {
+    let claim_id = LAST_CLAIM_ID.may_load(storage)?.unwrap_or_default() + 1;
+    LAST_CLAIM_ID.save(storage, &claim_id)?;
+    CLAIM_HISTORY.save(
+        storage,
+        (U64Key::new(grant_id), U64Key::new(claim_id)),
+        &ClaimRecord {
+            timestamp: block.time.seconds(),
+            height: block.height,
+            amount,
+            recipient: recipient.clone(),
+            kind,
+        },
+    )
+}
+
+/// Removes the grant with its pending transfer, and keeps it in the archive
+pub fn archive_vesting_account(
+    storage: &mut dyn Storage,
+    block: &BlockInfo,
+    account: VestingAccount,
+    reason: ArchiveReason,
+) -> StdResult<()> {
+    let address = account.address.clone();
+    let grant_id = account.grant_id;
+    vesting_accounts().remove(storage, (&address, U64Key::new(grant_id)))?;
+    PENDING_TRANSFERS.remove(storage, U64Key::new(grant_id));
+    ARCHIVED_ACCOUNTS.save(
+        storage,
+        (&address, U64Key::new(grant_id)),
+        &ArchivedAccount {
+            account,
+            reason,
+            archived_at: block.time.seconds(),
+        },
+    )
+}
@@ -1,16 +1,32 @@
-use crate::contract::{execute, instantiate, query};
+use crate::contract::{execute, instantiate, migrate, query};
+use crate::error::ContractError;
+use crate::migrations::{
+    self, PendingMasterAddressV4, VestingAccountV1, MASTER_ADDRESS_V4, PENDING_MASTER_ADDRESS_V4,
+    VESTING_ACCOUNTS_V1,
+};
+use crate::mock_querier;
 use crate::msg::{
-    CliffSchedule, Cw20HookMsg, ExecuteMsg, InstantiateMsg, MasterAddressResponse, QueryMsg,
-    VestingAccountResponse, VestingData, VestingSchedule,
+    AllVestingAccountsResponse, ArchiveReason, ArchivedVestingAccountsResponse,
+    ArchivedVestingData, ClaimHistoryResponse, ClaimKind, ClaimOperatorData,
+    ClaimOperatorsResponse, ClaimRecordData, CliffSchedule, ConfigResponse, Cw20HookMsg,
+    DelegationData, DelegationsResponse, DenomVotingPower, ExecuteMsg, InstantiateMsg,
+    LockedBalanceResponse, MigrateMsg, NewVestingAccount, PausedOperations, PausedPeriod,
+    PendingAdminData, PendingAdminsResponse, QueryMsg, Role, RoleMembersResponse, RolesResponse,
+    TimeUnit, TotalVotingPowerResponse, Unbonding, VestedAmountAtResponse, VestingAccountResponse,
+    VestingData, VestingSchedule, VestingTimelineResponse, VotingPowerResponse,
 };
 
+use crate::state::{vesting_accounts, VestingAccount, VOTING_POWER};
 use cosmwasm_std::{
     from_binary,
-    testing::{mock_dependencies, mock_env, mock_info},
-    to_binary, Addr, Attribute, BankMsg, Coin, Response, StdError, SubMsg, Timestamp, Uint128,
-    WasmMsg,
+    testing::{mock_dependencies, mock_env, mock_info, MOCK_CONTRACT_ADDR},
+    to_binary, Addr, Attribute, BankMsg, Coin, CosmosMsg, Deps, DistributionMsg, Env,
+    FullDelegation, OverflowError, OverflowOperation, Response, StakingMsg, StdError, SubMsg,
+    Timestamp, Uint128, Uint64, WasmMsg,
 };
-use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20ExecuteMsg, Cw20ReceiveMsg, Denom, Expiration};
+use cw_storage_plus::{Map, U64Key};
 
 #[test]
 fn proper_initialization() {
@@ -18,6 +34,8 @@ fn proper_initialization() {
 
     let msg = InstantiateMsg {
         master_address: None,
+        require_transfer_approval: None,
+        guardian: None,
     };
 
     let info = mock_info("addr0000", &[]);
@@ -27,49 +45,360 @@ fn proper_initialization() {
 }
 
 #[test]
-fn update_master_address() {
+fn manage_roles() {
     let mut deps = mock_dependencies(&[]);
 
     let msg = InstantiateMsg {
         master_address: None,
+        require_transfer_approval: None,
+        guardian: None,
     };
 
     let info = mock_info("addr0000", &[]);
+    let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-    // we can just call .unwrap() to assert this was a success
-    let _res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    // the master address holds every role
+    let roles = |deps: Deps, address: &str| {
+        from_binary::<RolesResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::Roles {
+                    address: address.to_string(),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .roles
+    };
+    assert_eq!(
+        roles(deps.as_ref(), "addr0000"),
+        vec![Role::Admin, Role::Registrar, Role::Revoker]
+    );
 
-    let msg = ExecuteMsg::UpdateMasterAddress {
-        master_address: "addr0001".to_string(),
+    // only an admin can grant roles
+    let msg = ExecuteMsg::GrantRole {
+        role: Role::Registrar,
+        address: "addr0001".to_string(),
     };
-    let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            ("action", "update_master_address"),
-            ("master_address", "addr0001"),
+            ("action", "grant_role"),
+            ("role", "registrar"),
+            ("address", "addr0001"),
         ]
     );
+    for (role, address) in [(Role::Registrar, "addr0002"), (Role::Revoker, "addr0003")] {
+        let msg = ExecuteMsg::GrantRole {
+            role,
+            address: address.to_string(),
+        };
+        let _ = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg).unwrap();
+    }
+    assert_eq!(roles(deps.as_ref(), "addr0001"), vec![Role::Registrar]);
+
+    // the admin role is proposed, and only taken over once accepted
+    let msg = ExecuteMsg::GrantRole {
+        role: Role::Admin,
+        address: "addr0004".to_string(),
+    };
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::AdminRequiresAcceptance {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let mut env = mock_env();
+    let msg = ExecuteMsg::ProposeAdmin {
+        address: "addr0004".to_string(),
+        expires: Some(Expiration::AtHeight(env.block.height)),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::InvalidExpiration {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::ProposeAdmin {
+        address: "addr0004".to_string(),
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "propose_admin"),
+            ("address", "addr0004"),
+            ("expires", "expiration height: 12355"),
+        ]
+    );
+    assert_eq!(roles(deps.as_ref(), "addr0004"), vec![]);
+    assert_eq!(
+        from_binary::<PendingAdminsResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PendingAdmins {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        PendingAdminsResponse {
+            pending_admins: vec![PendingAdminData {
+                address: "addr0004".to_string(),
+                expires: Expiration::AtHeight(env.block.height + 10),
+            }],
+        }
+    );
+
+    // only the proposed address can accept, before the proposal expires
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0005", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    );
+    match res.unwrap_err() {
+        ContractError::NoPendingAdmin { address } => assert_eq!(address, "addr0005"),
+        _ => panic!("should not enter"),
+    }
+
+    env.block.height += 10;
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0004", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    );
+    match res.unwrap_err() {
+        ContractError::AdminProposalExpired {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // a cancelled proposal cannot be accepted
+    let msg = ExecuteMsg::CancelAdminProposal {
+        address: "addr0004".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![("action", "cancel_admin_proposal"), ("address", "addr0004")]
+    );
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::NoPendingAdmin { address } => assert_eq!(address, "addr0004"),
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::ProposeAdmin {
+        address: "addr0004".to_string(),
+        expires: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("addr0004", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![("action", "accept_admin"), ("address", "addr0004")]
+    );
+    assert_eq!(roles(deps.as_ref(), "addr0004"), vec![Role::Admin]);
+    assert_eq!(
+        from_binary::<PendingAdminsResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::PendingAdmins {
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        PendingAdminsResponse {
+            pending_admins: vec![],
+        }
+    );
 
-    // query vesting account
     assert_eq!(
-        from_binary::<MasterAddressResponse>(
-            &query(deps.as_ref(), mock_env(), QueryMsg::MasterAddress {},).unwrap()
+        from_binary::<RoleMembersResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::RoleMembers {
+                    role: Role::Registrar,
+                    start_after: Some("addr0000".to_string()),
+                    limit: None,
+                },
+            )
+            .unwrap()
         )
         .unwrap(),
-        MasterAddressResponse {
-            master_address: "addr0001".to_string(),
+        RoleMembersResponse {
+            role: Role::Registrar,
+            members: vec!["addr0001".to_string(), "addr0002".to_string()],
         }
     );
 
-    let msg = ExecuteMsg::UpdateMasterAddress {
-        master_address: "addr0002".to_string(),
+    // each role is limited to its own operations
+    let env = mock_env();
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0005".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(env.block.time.seconds()),
+            end_time: Uint64::new(env.block.time.seconds() + 100),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0003", &[Coin::new(1000000u128, "uusd")]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[Coin::new(1000000u128, "uusd")]),
+        msg,
+    )
+    .unwrap();
+
+    let msg = ExecuteMsg::RevokeVestingAccount {
+        address: "addr0005".to_string(),
+        grant_id: 1,
+        left_vesting_token_recipient: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let res = execute(deps.as_mut(), env, mock_info("addr0003", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0003".to_string(),
+            amount: vec![Coin::new(1000000u128, "uusd")],
+        })]
+    );
+
+    let msg = ExecuteMsg::UpdateConfig {
+        require_transfer_approval: Some(true),
+        unbonding_period: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let _ = execute(deps.as_mut(), mock_env(), mock_info("addr0004", &[]), msg).unwrap();
+
+    // revoke roles
+    let msg = ExecuteMsg::RevokeRole {
+        role: Role::Registrar,
+        address: "addr0001".to_string(),
     };
-    let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
-    match res {
-        StdError::GenericErr { msg, .. } => assert_eq!(msg, "unauthorized"),
-        _ => panic!("DO NOT ENTER HERE"),
+    let res = execute(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0004", &[]),
+        msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "revoke_role"),
+            ("role", "registrar"),
+            ("address", "addr0001"),
+        ]
+    );
+    assert_eq!(roles(deps.as_ref(), "addr0001"), vec![]);
+
+    let res = execute(deps.as_mut(), mock_env(), mock_info("addr0004", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::RoleNotFound { role, address } => {
+            assert_eq!(role, "registrar");
+            assert_eq!(address, "addr0001");
+        }
+        _ => panic!("should not enter"),
     }
+
+    // the former admin can be revoked once the new one accepted, but never the last one
+    for (sender, address) in [("addr0004", "addr0000"), ("addr0004", "addr0004")] {
+        let msg = ExecuteMsg::RevokeRole {
+            role: Role::Admin,
+            address: address.to_string(),
+        };
+        let res = execute(deps.as_mut(), mock_env(), mock_info(sender, &[]), msg);
+        if address == "addr0000" {
+            res.unwrap();
+        } else {
+            match res.unwrap_err() {
+                ContractError::LastAdmin {} => {}
+                _ => panic!("should not enter"),
+            }
+        }
+    }
+    assert_eq!(
+        roles(deps.as_ref(), "addr0000"),
+        vec![Role::Registrar, Role::Revoker]
+    );
+    assert_eq!(roles(deps.as_ref(), "addr0004"), vec![Role::Admin]);
 }
 
 #[test]
@@ -81,6 +410,8 @@ fn register_vesting_account_with_native_token() {
         mock_info("addr0000", &[]),
         InstantiateMsg {
             master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
         },
     )
     .unwrap();
@@ -92,19 +423,18 @@ fn register_vesting_account_with_native_token() {
     let msg = ExecuteMsg::RegisterVestingAccount {
         address: "addr0001".to_string(),
         vesting_schedule: VestingSchedule::LinearVesting {
-            start_time: "100".to_string(),
-            end_time: "110".to_string(),
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
             vesting_amount: Uint128::zero(),
         },
+        time_unit: None,
     };
 
     // invalid zero amount
     let info = mock_info("addr0000", &[Coin::new(0u128, "uusd")]);
     let res = execute(deps.as_mut(), env.clone(), info, msg);
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => {
-            assert_eq!(msg, "assert(deposit_amount > 0)")
-        }
+        ContractError::ZeroDepositAmount {} => {}
         _ => panic!("should not enter"),
     }
 
@@ -112,17 +442,18 @@ fn register_vesting_account_with_native_token() {
     let msg = ExecuteMsg::RegisterVestingAccount {
         address: "addr0001".to_string(),
         vesting_schedule: VestingSchedule::LinearVesting {
-            start_time: "100".to_string(),
-            end_time: "110".to_string(),
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
             vesting_amount: Uint128::new(1000000u128),
         },
+        time_unit: None,
     };
 
     // invalid amount
     let info = mock_info("addr0000", &[]);
     let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => assert_eq!(msg, "must deposit only one type of token"),
+        ContractError::InvalidFunds {} => {}
         _ => panic!("should not enter"),
     }
 
@@ -133,7 +464,7 @@ fn register_vesting_account_with_native_token() {
     );
     let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => assert_eq!(msg, "must deposit only one type of token"),
+        ContractError::InvalidFunds {} => {}
         _ => panic!("should not enter"),
     }
 
@@ -141,8 +472,12 @@ fn register_vesting_account_with_native_token() {
     let info = mock_info("addr0000", &[Coin::new(10u128, "uusd")]);
     let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => {
-            assert_eq!(msg, "assert(deposit_amount == vesting_amount)")
+        ContractError::DepositAmountMismatch {
+            deposit_amount,
+            vesting_amount,
+        } => {
+            assert_eq!(deposit_amount, Uint128::new(10u128));
+            assert_eq!(vesting_amount, Uint128::new(1000000u128));
         }
         _ => panic!("should not enter"),
     }
@@ -155,6 +490,7 @@ fn register_vesting_account_with_native_token() {
         vec![
             ("action", "register_vesting_account"),
             ("address", "addr0001"),
+            ("grant_id", "1"),
             ("vesting_denom", "{\"native\":\"uusd\"}"),
             ("vesting_amount", "1000000"),
         ]
@@ -178,15 +514,19 @@ fn register_vesting_account_with_native_token() {
         VestingAccountResponse {
             address: "addr0001".to_string(),
             vestings: vec![VestingData {
+                grant_id: 1,
                 vesting_denom: Denom::Native("uusd".to_string()),
                 vesting_amount: Uint128::new(1000000),
                 vested_amount: Uint128::zero(),
                 vesting_schedule: VestingSchedule::LinearVesting {
-                    start_time: "100".to_string(),
-                    end_time: "110".to_string(),
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
                     vesting_amount: Uint128::new(1000000u128),
                 },
                 claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
             }],
         }
     );
@@ -201,6 +541,8 @@ fn register_vesting_account_with_cw20_token() {
         mock_info("addr0000", &[]),
         InstantiateMsg {
             master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
         },
     )
     .unwrap();
@@ -215,10 +557,11 @@ fn register_vesting_account_with_cw20_token() {
         msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
             address: "addr0001".to_string(),
             vesting_schedule: VestingSchedule::LinearVesting {
-                start_time: "100".to_string(),
-                end_time: "110".to_string(),
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
                 vesting_amount: Uint128::zero(),
             },
+            time_unit: None,
         })
         .unwrap(),
     });
@@ -226,9 +569,7 @@ fn register_vesting_account_with_cw20_token() {
     // invalid zero amount
     let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => {
-            assert_eq!(msg, "assert(deposit_amount > 0)")
-        }
+        ContractError::ZeroDepositAmount {} => {}
         _ => panic!("should not enter"),
     }
 
@@ -239,10 +580,11 @@ fn register_vesting_account_with_cw20_token() {
         msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
             address: "addr0001".to_string(),
             vesting_schedule: VestingSchedule::LinearVesting {
-                start_time: "100".to_string(),
-                end_time: "110".to_string(),
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
                 vesting_amount: Uint128::new(999000u128),
             },
+            time_unit: None,
         })
         .unwrap(),
     });
@@ -250,9 +592,7 @@ fn register_vesting_account_with_cw20_token() {
     // invalid amount
     let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => {
-            assert_eq!(msg, "assert(deposit_amount == vesting_amount)")
-        }
+        ContractError::DepositAmountMismatch { .. } => {}
         _ => panic!("should not enter"),
     }
 
@@ -263,10 +603,11 @@ fn register_vesting_account_with_cw20_token() {
         msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
             address: "addr0001".to_string(),
             vesting_schedule: VestingSchedule::LinearVesting {
-                start_time: "100".to_string(),
-                end_time: "110".to_string(),
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
                 vesting_amount: Uint128::new(1000000u128),
             },
+            time_unit: None,
         })
         .unwrap(),
     });
@@ -278,6 +619,7 @@ fn register_vesting_account_with_cw20_token() {
         vec![
             ("action", "register_vesting_account"),
             ("address", "addr0001"),
+            ("grant_id", "1"),
             ("vesting_denom", "{\"cw20\":\"token0000\"}"),
             ("vesting_amount", "1000000"),
         ]
@@ -301,22 +643,26 @@ fn register_vesting_account_with_cw20_token() {
         VestingAccountResponse {
             address: "addr0001".to_string(),
             vestings: vec![VestingData {
+                grant_id: 1,
                 vesting_denom: Denom::Cw20(Addr::unchecked("token0000")),
                 vesting_amount: Uint128::new(1000000),
                 vested_amount: Uint128::zero(),
                 vesting_schedule: VestingSchedule::LinearVesting {
-                    start_time: "100".to_string(),
-                    end_time: "110".to_string(),
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
                     vesting_amount: Uint128::new(1000000u128),
                 },
                 claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
             }],
         }
     );
 }
 
 #[test]
-fn claim_native() {
+fn register_vesting_accounts_with_native_token() {
     let mut deps = mock_dependencies(&[]);
     let _res = instantiate(
         deps.as_mut(),
@@ -324,86 +670,104 @@ fn claim_native() {
         mock_info("addr0000", &[]),
         InstantiateMsg {
             master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
         },
     )
     .unwrap();
 
-    // init env to time 100
     let mut env = mock_env();
     env.block.time = Timestamp::from_seconds(100);
 
-    // valid amount
-    let msg = ExecuteMsg::RegisterVestingAccount {
-        address: "addr0001".to_string(),
-        vesting_schedule: VestingSchedule::LinearVesting {
-            start_time: "100".to_string(),
-            end_time: "110".to_string(),
-            vesting_amount: Uint128::new(1000000u128),
-        },
+    // empty vesting accounts
+    let msg = ExecuteMsg::RegisterVestingAccounts {
+        vesting_accounts: vec![],
     };
-
     let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
-    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
-
-    // make time to half claimable
-    env.block.time = Timestamp::from_seconds(105);
+    let res = execute(deps.as_mut(), env.clone(), info, msg);
+    match res.unwrap_err() {
+        ContractError::EmptyVestingAccounts {} => {}
+        _ => panic!("should not enter"),
+    }
 
-    // claim not found denom
-    let msg = ExecuteMsg::Claim {
-        denoms: vec![
-            Denom::Native("ukrw".to_string()),
-            Denom::Native("uusd".to_string()),
+    let msg = ExecuteMsg::RegisterVestingAccounts {
+        vesting_accounts: vec![
+            NewVestingAccount {
+                address: "addr0001".to_string(),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                time_unit: None,
+            },
+            NewVestingAccount {
+                address: "addr0002".to_string(),
+                vesting_schedule: VestingSchedule::PeriodicVesting {
+                    start_time: Uint64::new(105),
+                    end_time: Uint64::new(110),
+                    vesting_interval: Uint64::new(5),
+                    amount: Uint128::new(500000u128),
+                },
+                time_unit: None,
+            },
         ],
-        recipient: None,
     };
 
-    let info = mock_info("addr0001", &[]);
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    // invalid funds
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => assert_eq!(
-            msg,
-            "vesting entry is not found for denom {\"native\":\"ukrw\"}"
-        ),
+        ContractError::InvalidFunds {} => {}
         _ => panic!("should not enter"),
     }
 
-    // valid claim
-    let msg = ExecuteMsg::Claim {
-        denoms: vec![Denom::Native("uusd".to_string())],
-        recipient: None,
-    };
+    // deposit does not cover all vesting accounts
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    match res.unwrap_err() {
+        ContractError::DepositAmountMismatch {
+            deposit_amount,
+            vesting_amount,
+        } => {
+            assert_eq!(deposit_amount, Uint128::new(1000000u128));
+            assert_eq!(vesting_amount, Uint128::new(2000000u128));
+        }
+        _ => panic!("should not enter"),
+    }
 
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(BankMsg::Send {
-            to_address: "addr0001".to_string(),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::new(500000u128),
-            }],
-        }),]
-    );
+    // only master can register
+    let info = mock_info("addr0001", &[Coin::new(2000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let info = mock_info("addr0000", &[Coin::new(2000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            Attribute::new("action", "claim"),
-            Attribute::new("address", "addr0001"),
-            Attribute::new("vesting_denom", "{\"native\":\"uusd\"}"),
-            Attribute::new("vesting_amount", "1000000"),
-            Attribute::new("vested_amount", "500000"),
-            Attribute::new("claim_amount", "500000"),
-        ],
+            ("action", "register_vesting_accounts"),
+            ("vesting_denom", "{\"native\":\"uusd\"}"),
+            ("deposit_amount", "2000000"),
+            ("address", "addr0001"),
+            ("grant_id", "1"),
+            ("vesting_amount", "1000000"),
+            ("address", "addr0002"),
+            ("grant_id", "2"),
+            ("vesting_amount", "1000000"),
+        ]
     );
 
-    // query vesting account
     assert_eq!(
         from_binary::<VestingAccountResponse>(
             &query(
                 deps.as_ref(),
-                env.clone(),
+                env,
                 QueryMsg::VestingAccount {
-                    address: "addr0001".to_string(),
+                    address: "addr0002".to_string(),
                     start_after: None,
                     limit: None,
                 },
@@ -412,71 +776,29 @@ fn claim_native() {
         )
         .unwrap(),
         VestingAccountResponse {
-            address: "addr0001".to_string(),
+            address: "addr0002".to_string(),
             vestings: vec![VestingData {
+                grant_id: 2,
                 vesting_denom: Denom::Native("uusd".to_string()),
                 vesting_amount: Uint128::new(1000000),
-                vested_amount: Uint128::new(500000),
-                vesting_schedule: VestingSchedule::LinearVesting {
-                    start_time: "100".to_string(),
-                    end_time: "110".to_string(),
-                    vesting_amount: Uint128::new(1000000u128),
+                vested_amount: Uint128::zero(),
+                vesting_schedule: VestingSchedule::PeriodicVesting {
+                    start_time: Uint64::new(105),
+                    end_time: Uint64::new(110),
+                    vesting_interval: Uint64::new(5),
+                    amount: Uint128::new(500000u128),
                 },
                 claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
             }],
         }
     );
-
-    // make time to half claimable
-    env.block.time = Timestamp::from_seconds(110);
-
-    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(BankMsg::Send {
-            to_address: "addr0001".to_string(),
-            amount: vec![Coin {
-                denom: "uusd".to_string(),
-                amount: Uint128::new(500000u128),
-            }],
-        }),]
-    );
-    assert_eq!(
-        res.attributes,
-        vec![
-            Attribute::new("action", "claim"),
-            Attribute::new("address", "addr0001"),
-            Attribute::new("vesting_denom", "{\"native\":\"uusd\"}"),
-            Attribute::new("vesting_amount", "1000000"),
-            Attribute::new("vested_amount", "1000000"),
-            Attribute::new("claim_amount", "500000"),
-        ],
-    );
-
-    // query vesting account
-    assert_eq!(
-        from_binary::<VestingAccountResponse>(
-            &query(
-                deps.as_ref(),
-                env,
-                QueryMsg::VestingAccount {
-                    address: "addr0001".to_string(),
-                    start_after: None,
-                    limit: None,
-                },
-            )
-            .unwrap()
-        )
-        .unwrap(),
-        VestingAccountResponse {
-            address: "addr0001".to_string(),
-            vestings: vec![],
-        }
-    );
-}
+}
 
 #[test]
-fn claim_cw20() {
+fn register_vesting_accounts_with_cw20_token() {
     let mut deps = mock_dependencies(&[]);
     let _res = instantiate(
         deps.as_mut(),
@@ -484,87 +806,178 @@ fn claim_cw20() {
         mock_info("addr0000", &[]),
         InstantiateMsg {
             master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
         },
     )
     .unwrap();
 
-    // init env to time 100
     let mut env = mock_env();
     env.block.time = Timestamp::from_seconds(100);
 
-    // valid amount
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "addr0000".to_string(),
-        amount: Uint128::new(1000000u128),
-        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+    let vesting_accounts = vec![
+        NewVestingAccount {
             address: "addr0001".to_string(),
             vesting_schedule: VestingSchedule::LinearVesting {
-                start_time: "100".to_string(),
-                end_time: "110".to_string(),
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
                 vesting_amount: Uint128::new(1000000u128),
             },
+            time_unit: None,
+        },
+        NewVestingAccount {
+            address: "addr0002".to_string(),
+            vesting_schedule: VestingSchedule::CliffVesting {
+                schedules: vec![CliffSchedule {
+                    release_time: Uint64::new(105),
+                    release_amount: Uint128::new(500000u128),
+                }],
+            },
+            time_unit: None,
+        },
+    ];
+
+    // invalid schedule in the batch
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1500000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccounts {
+            vesting_accounts: vec![
+                vesting_accounts[0].clone(),
+                NewVestingAccount {
+                    address: "addr0002".to_string(),
+                    vesting_schedule: VestingSchedule::CliffVesting {
+                        schedules: vec![CliffSchedule {
+                            release_time: Uint64::new(99),
+                            release_amount: Uint128::new(500000u128),
+                        }],
+                    },
+                    time_unit: None,
+                },
+            ],
         })
         .unwrap(),
     });
+    let info = mock_info("token0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res.unwrap_err() {
+        ContractError::TimeBeforeBlockTime { name, .. } => assert_eq!(name, "release_time"),
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1500000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccounts { vesting_accounts }).unwrap(),
+    });
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "register_vesting_accounts"),
+            ("vesting_denom", "{\"cw20\":\"token0000\"}"),
+            ("deposit_amount", "1500000"),
+            ("address", "addr0001"),
+            ("grant_id", "1"),
+            ("vesting_amount", "1000000"),
+            ("address", "addr0002"),
+            ("grant_id", "2"),
+            ("vesting_amount", "500000"),
+        ]
+    );
+}
 
-    // valid amount
-    let info = mock_info("token0001", &[]);
+#[test]
+fn top_up_vesting_account() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
     let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    // make time to half claimable
     env.block.time = Timestamp::from_seconds(105);
-
-    // claim not found denom
     let msg = ExecuteMsg::Claim {
-        denoms: vec![
-            Denom::Cw20(Addr::unchecked("token0002")),
-            Denom::Cw20(Addr::unchecked("token0001")),
-        ],
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
         recipient: None,
     };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
 
-    let info = mock_info("addr0001", &[]);
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    let msg = ExecuteMsg::TopUpVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        schedules: None,
+    };
+
+    // unauthorized
+    let info = mock_info("addr0001", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => assert_eq!(
-            msg,
-            "vesting entry is not found for denom {\"cw20\":\"token0002\"}"
-        ),
+        ContractError::Unauthorized {} => {}
         _ => panic!("should not enter"),
     }
 
-    // valid claim
-    let msg = ExecuteMsg::Claim {
-        denoms: vec![Denom::Cw20(Addr::unchecked("token0001"))],
-        recipient: None,
-    };
+    // denom mismatch
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "ukrw")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    match res.unwrap_err() {
+        ContractError::VestingDenomMismatch { .. } => {}
+        _ => panic!("should not enter"),
+    }
 
-    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
-    assert_eq!(
-        res.messages,
-        vec![SubMsg::new(WasmMsg::Execute {
-            contract_addr: "token0001".to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: "addr0001".to_string(),
-                amount: Uint128::new(500000u128),
-            })
-            .unwrap(),
-        }),]
+    // grant not found
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        ExecuteMsg::TopUpVestingAccount {
+            address: "addr0001".to_string(),
+            grant_id: 2,
+            schedules: None,
+        },
     );
+    match res.unwrap_err() {
+        ContractError::VestingNotFound { grant_id } => assert_eq!(grant_id, 2),
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            Attribute::new("action", "claim"),
-            Attribute::new("address", "addr0001"),
-            Attribute::new("vesting_denom", "{\"cw20\":\"token0001\"}"),
-            Attribute::new("vesting_amount", "1000000"),
-            Attribute::new("vested_amount", "500000"),
-            Attribute::new("claim_amount", "500000"),
-        ],
+            ("action", "top_up_vesting_account"),
+            ("address", "addr0001"),
+            ("grant_id", "1"),
+            ("vesting_denom", "{\"native\":\"uusd\"}"),
+            ("top_up_amount", "1000000"),
+            ("vesting_amount", "2000000"),
+        ]
     );
 
-    // query vesting account
+    // claimed amount is kept, so only the newly vested amount is claimable
     assert_eq!(
         from_binary::<VestingAccountResponse>(
             &query(
@@ -582,55 +995,143 @@ fn claim_cw20() {
         VestingAccountResponse {
             address: "addr0001".to_string(),
             vestings: vec![VestingData {
-                vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
-                vesting_amount: Uint128::new(1000000),
-                vested_amount: Uint128::new(500000),
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(2000000u128),
+                vested_amount: Uint128::new(1000000u128),
                 vesting_schedule: VestingSchedule::LinearVesting {
-                    start_time: "100".to_string(),
-                    end_time: "110".to_string(),
-                    vesting_amount: Uint128::new(1000000u128),
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(2000000u128),
                 },
-                claimable_amount: Uint128::zero(),
+                claimable_amount: Uint128::new(500000u128),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
             }],
         }
     );
 
-    // make time to half claimable
-    env.block.time = Timestamp::from_seconds(110);
+    // cw20 top-up of a native grant
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1000000u128),
+        msg: to_binary(&Cw20HookMsg::TopUpVestingAccount {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+            schedules: None,
+        })
+        .unwrap(),
+    });
+    let res = execute(deps.as_mut(), env.clone(), mock_info("token0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::VestingDenomMismatch {
+            deposit_denom,
+            vesting_denom,
+        } => {
+            assert_eq!(deposit_denom, "{\"cw20\":\"token0000\"}");
+            assert_eq!(vesting_denom, "{\"native\":\"uusd\"}");
+        }
+        _ => panic!("should not enter"),
+    }
 
-    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    // the whole grant is claimed at the end
+    env.block.time = Timestamp::from_seconds(110);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("addr0001", &[]), msg).unwrap();
     assert_eq!(
         res.messages,
-        vec![SubMsg::new(WasmMsg::Execute {
-            contract_addr: "token0001".to_string(),
-            funds: vec![],
-            msg: to_binary(&Cw20ExecuteMsg::Transfer {
-                recipient: "addr0001".to_string(),
-                amount: Uint128::new(500000u128),
-            })
-            .unwrap(),
-        }),]
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1500000u128),
+            }],
+        })]
+    );
+}
+
+#[test]
+fn transfer_vesting_account() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    for _ in 0..2 {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: vesting_schedule.clone(),
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    env.block.time = Timestamp::from_seconds(105);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: Some(vec![1]),
+        recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    // only the beneficiary can transfer the grant
+    let msg = ExecuteMsg::TransferVestingAccount {
+        grant_id: 1,
+        new_address: "addr0002".to_string(),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
     );
+    match res.unwrap_err() {
+        ContractError::VestingNotFound { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            Attribute::new("action", "claim"),
-            Attribute::new("address", "addr0001"),
-            Attribute::new("vesting_denom", "{\"cw20\":\"token0001\"}"),
-            Attribute::new("vesting_amount", "1000000"),
-            Attribute::new("vested_amount", "1000000"),
-            Attribute::new("claim_amount", "500000"),
-        ],
+            ("action", "transfer_vesting_account"),
+            ("address", "addr0001"),
+            ("grant_id", "1"),
+            ("new_address", "addr0002"),
+            ("status", "transferred"),
+        ]
     );
 
-    // query vesting account
+    // schedule and claimed amount are kept
     assert_eq!(
         from_binary::<VestingAccountResponse>(
             &query(
                 deps.as_ref(),
-                env,
+                env.clone(),
                 QueryMsg::VestingAccount {
-                    address: "addr0001".to_string(),
+                    address: "addr0002".to_string(),
                     start_after: None,
                     limit: None,
                 },
@@ -639,14 +1140,142 @@ fn claim_cw20() {
         )
         .unwrap(),
         VestingAccountResponse {
-            address: "addr0001".to_string(),
-            vestings: vec![],
+            address: "addr0002".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000u128),
+                vested_amount: Uint128::new(500000u128),
+                vesting_schedule: vesting_schedule.clone(),
+                claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
         }
     );
-}
 
-#[test]
-fn query_vesting_account() {
+    // require master approval
+    let msg = ExecuteMsg::UpdateConfig {
+        require_transfer_approval: Some(true),
+        unbonding_period: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        from_binary::<ConfigResponse>(
+            &query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()
+        )
+        .unwrap(),
+        ConfigResponse {
+            require_transfer_approval: true,
+            guardian: None,
+            paused: PausedOperations::default(),
+            unbonding_period: 1814400,
+        }
+    );
+
+    let msg = ExecuteMsg::ApproveVestingTransfer { grant_id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::NoPendingTransfer { grant_id } => assert_eq!(grant_id, 2),
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::TransferVestingAccount {
+        grant_id: 2,
+        new_address: "addr0003".to_string(),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(res.attributes[4], ("status", "pending"));
+
+    // not moved until approved
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: Some(vec![2]),
+        recipient: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0003", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::VestingNotFound { grant_id } => assert_eq!(grant_id, 2),
+        _ => panic!("should not enter"),
+    }
+
+    let approve_msg = ExecuteMsg::ApproveVestingTransfer { grant_id: 2 };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        approve_msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        approve_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "approve_vesting_transfer"),
+            ("address", "addr0001"),
+            ("grant_id", "2"),
+            ("new_address", "addr0003"),
+        ]
+    );
+
+    // the pending transfer is consumed
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        approve_msg,
+    );
+    match res.unwrap_err() {
+        ContractError::NoPendingTransfer { grant_id } => assert_eq!(grant_id, 2),
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(deps.as_mut(), env, mock_info("addr0003", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0003".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        })]
+    );
+}
+
+#[test]
+fn revoke_vesting_account() {
     let mut deps = mock_dependencies(&[]);
     let _res = instantiate(
         deps.as_mut(),
@@ -654,93 +1283,109 @@ fn query_vesting_account() {
         mock_info("addr0000", &[]),
         InstantiateMsg {
             master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
         },
     )
     .unwrap();
 
-    // init env to time 100
     let mut env = mock_env();
     env.block.time = Timestamp::from_seconds(100);
 
-    // native vesting
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
     let msg = ExecuteMsg::RegisterVestingAccount {
         address: "addr0001".to_string(),
-        vesting_schedule: VestingSchedule::LinearVesting {
-            start_time: "100".to_string(),
-            end_time: "110".to_string(),
-            vesting_amount: Uint128::new(1000000u128),
-        },
+        vesting_schedule: vesting_schedule.clone(),
+        time_unit: None,
     };
-
     let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
     let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
-        sender: "addr0000".to_string(),
-        amount: Uint128::new(1000000u128),
-        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
-            address: "addr0001".to_string(),
-            vesting_schedule: VestingSchedule::LinearVesting {
-                start_time: "100".to_string(),
-                end_time: "110".to_string(),
-                vesting_amount: Uint128::new(1000000u128),
-            },
-        })
-        .unwrap(),
-    });
-
-    // valid amount
-    let info = mock_info("token0001", &[]);
-    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    env.block.time = Timestamp::from_seconds(104);
+    let claim_msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg.clone(),
+    )
+    .unwrap();
 
-    // half claimable
     env.block.time = Timestamp::from_seconds(105);
+    let msg = ExecuteMsg::RevokeVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        left_vesting_token_recipient: Some("addr0002".to_string()),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
 
-    // query all entry
+    // only the unvested amount leaves the contract
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        msg.clone(),
+    )
+    .unwrap();
     assert_eq!(
-        from_binary::<VestingAccountResponse>(
-            &query(
-                deps.as_ref(),
-                env.clone(),
-                QueryMsg::VestingAccount {
-                    address: "addr0001".to_string(),
-                    start_after: None,
-                    limit: None,
-                },
-            )
-            .unwrap()
-        )
-        .unwrap(),
-        VestingAccountResponse {
-            address: "addr0001".to_string(),
-            vestings: vec![
-                VestingData {
-                    vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
-                    vesting_amount: Uint128::new(1000000),
-                    vested_amount: Uint128::new(500000),
-                    vesting_schedule: VestingSchedule::LinearVesting {
-                        start_time: "100".to_string(),
-                        end_time: "110".to_string(),
-                        vesting_amount: Uint128::new(1000000u128),
-                    },
-                    claimable_amount: Uint128::new(500000),
-                },
-                VestingData {
-                    vesting_denom: Denom::Native("uusd".to_string()),
-                    vesting_amount: Uint128::new(1000000),
-                    vested_amount: Uint128::new(500000),
-                    vesting_schedule: VestingSchedule::LinearVesting {
-                        start_time: "100".to_string(),
-                        end_time: "110".to_string(),
-                        vesting_amount: Uint128::new(1000000u128),
-                    },
-                    claimable_amount: Uint128::new(500000),
-                }
-            ],
-        }
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0002".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        })]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "revoke_vesting_account"),
+            ("address", "addr0001"),
+            ("grant_id", "1"),
+            ("vesting_denom", "{\"native\":\"uusd\"}"),
+            ("vested_amount", "500000"),
+            ("left_vesting_amount", "500000"),
+        ]
     );
 
-    // query one entry
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::VestingRevoked { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::TopUpVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        schedules: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg);
+    match res.unwrap_err() {
+        ContractError::VestingRevoked { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+
+    // nothing more is vested after the revocation
+    env.block.time = Timestamp::from_seconds(110);
     assert_eq!(
         from_binary::<VestingAccountResponse>(
             &query(
@@ -749,7 +1394,7 @@ fn query_vesting_account() {
                 QueryMsg::VestingAccount {
                     address: "addr0001".to_string(),
                     start_after: None,
-                    limit: Some(1),
+                    limit: None,
                 },
             )
             .unwrap()
@@ -758,20 +1403,38 @@ fn query_vesting_account() {
         VestingAccountResponse {
             address: "addr0001".to_string(),
             vestings: vec![VestingData {
-                vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
-                vesting_amount: Uint128::new(1000000),
-                vested_amount: Uint128::new(500000),
-                vesting_schedule: VestingSchedule::LinearVesting {
-                    start_time: "100".to_string(),
-                    end_time: "110".to_string(),
-                    vesting_amount: Uint128::new(1000000u128),
-                },
-                claimable_amount: Uint128::new(500000),
-            },],
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(500000u128),
+                vested_amount: Uint128::new(500000u128),
+                vesting_schedule,
+                time_unit: TimeUnit::Seconds,
+                revoked_at: Some(105),
+                paused_periods: vec![],
+                claimable_amount: Uint128::new(100000u128),
+            }],
         }
     );
 
-    // query one entry after first one
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(100000u128),
+            }],
+        })]
+    );
+
+    // fully claimed grant is removed
     assert_eq!(
         from_binary::<VestingAccountResponse>(
             &query(
@@ -779,32 +1442,20 @@ fn query_vesting_account() {
                 env,
                 QueryMsg::VestingAccount {
                     address: "addr0001".to_string(),
-                    start_after: Some(Denom::Cw20(Addr::unchecked("token0001"))),
-                    limit: Some(1),
+                    start_after: None,
+                    limit: None,
                 },
             )
             .unwrap()
         )
-        .unwrap(),
-        VestingAccountResponse {
-            address: "addr0001".to_string(),
-            vestings: vec![VestingData {
-                vesting_denom: Denom::Native("uusd".to_string()),
-                vesting_amount: Uint128::new(1000000),
-                vested_amount: Uint128::new(500000),
-                vesting_schedule: VestingSchedule::LinearVesting {
-                    start_time: "100".to_string(),
-                    end_time: "110".to_string(),
-                    vesting_amount: Uint128::new(1000000u128),
-                },
-                claimable_amount: Uint128::new(500000),
-            }],
-        }
+        .unwrap()
+        .vestings,
+        vec![]
     );
 }
 
 #[test]
-fn register_cliff_vesting_account_with_native_token() {
+fn claim_native() {
     let mut deps = mock_dependencies(&[]);
     let _res = instantiate(
         deps.as_mut(),
@@ -812,134 +1463,4809 @@ fn register_cliff_vesting_account_with_native_token() {
         mock_info("addr0000", &[]),
         InstantiateMsg {
             master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
         },
     )
     .unwrap();
 
+    // init env to time 100
     let mut env = mock_env();
     env.block.time = Timestamp::from_seconds(100);
 
-    // zero amount vesting token
+    // valid amount
     let msg = ExecuteMsg::RegisterVestingAccount {
         address: "addr0001".to_string(),
-        vesting_schedule: VestingSchedule::CliffVesting {
-            schedules: vec![
-                CliffSchedule {
-                    release_time: "105".to_string(),
-                    release_amount: Uint128::zero(),
-                },
-                CliffSchedule {
-                    release_time: "110".to_string(),
-                    release_amount: Uint128::zero(),
-                },
-            ],
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
         },
+        time_unit: None,
     };
 
-    // invalid zero amount
-    let info = mock_info("addr0000", &[Coin::new(0u128, "uusd")]);
-    let res = execute(deps.as_mut(), env.clone(), info, msg);
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // make time to half claimable
+    env.block.time = Timestamp::from_seconds(105);
+
+    // claim not found denom
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![
+            Denom::Native("ukrw".to_string()),
+            Denom::Native("uusd".to_string()),
+        ],
+        grant_ids: None,
+        recipient: None,
+    };
+
+    let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => {
-            assert_eq!(msg, "assert(deposit_amount > 0)")
-        }
+        ContractError::DenomNotFound { denom } => assert_eq!(denom, "{\"native\":\"ukrw\"}"),
         _ => panic!("should not enter"),
     }
 
-    // normal amount vesting token
-    let msg = ExecuteMsg::RegisterVestingAccount {
-        address: "addr0001".to_string(),
-        vesting_schedule: VestingSchedule::CliffVesting {
-            schedules: vec![
-                CliffSchedule {
-                    release_time: "105".to_string(),
+    // valid claim
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        }),]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "claim"),
+            Attribute::new("address", "addr0001"),
+            Attribute::new("grant_id", "1"),
+            Attribute::new("vesting_denom", "{\"native\":\"uusd\"}"),
+            Attribute::new("vesting_amount", "1000000"),
+            Attribute::new("vested_amount", "500000"),
+            Attribute::new("claim_amount", "500000"),
+        ],
+    );
+
+    // query vesting account
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::new(500000),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+
+    // make time to half claimable
+    env.block.time = Timestamp::from_seconds(110);
+
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        }),]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "claim"),
+            Attribute::new("address", "addr0001"),
+            Attribute::new("grant_id", "1"),
+            Attribute::new("vesting_denom", "{\"native\":\"uusd\"}"),
+            Attribute::new("vesting_amount", "1000000"),
+            Attribute::new("vested_amount", "1000000"),
+            Attribute::new("claim_amount", "500000"),
+        ],
+    );
+
+    // query vesting account
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![],
+        }
+    );
+}
+
+#[test]
+fn claim_all() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    for denom in ["uusd", "ukrw"] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: VestingSchedule::LinearVesting {
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
+                vesting_amount: Uint128::new(1000000u128),
+            },
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000000u128, denom)]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    // nothing is vested before the cliff
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1000000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: VestingSchedule::CliffVesting {
+                schedules: vec![CliffSchedule {
+                    release_time: Uint64::new(120),
+                    release_amount: Uint128::new(1000000u128),
+                }],
+            },
+            time_unit: None,
+        })
+        .unwrap(),
+    });
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("token0000", &[]), msg).unwrap();
+
+    env.block.time = Timestamp::from_seconds(105);
+    let msg = ExecuteMsg::ClaimAll { recipient: None };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(BankMsg::Send {
+                to_address: "addr0001".to_string(),
+                amount: vec![Coin {
+                    denom: "uusd".to_string(),
+                    amount: Uint128::new(500000u128),
+                }],
+            }),
+            SubMsg::new(BankMsg::Send {
+                to_address: "addr0001".to_string(),
+                amount: vec![Coin {
+                    denom: "ukrw".to_string(),
+                    amount: Uint128::new(500000u128),
+                }],
+            }),
+        ]
+    );
+    assert_eq!(
+        res.attributes[..2],
+        vec![("action", "claim_all"), ("address", "addr0001")]
+    );
+
+    // addresses without grants claim nothing
+    let msg = ExecuteMsg::ClaimAll {
+        recipient: Some("addr0003".to_string()),
+    };
+    let res = execute(deps.as_mut(), env, mock_info("addr0002", &[]), msg).unwrap();
+    assert_eq!(res.messages, vec![]);
+}
+
+#[test]
+fn claim_duplicate_denoms() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    for address in ["addr0001", "addr0002"] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: address.to_string(),
+            vesting_schedule: VestingSchedule::LinearVesting {
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
+                vesting_amount: Uint128::new(1000000u128),
+            },
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    // a repeated denom is claimed only once
+    env.block.time = Timestamp::from_seconds(105);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![
+            Denom::Native("uusd".to_string()),
+            Denom::Native("uusd".to_string()),
+        ],
+        grant_ids: None,
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(500000u128, "uusd")],
+        })]
+    );
+
+    let res: LockedBalanceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::LockedBalance {
+                denom: Denom::Native("uusd".to_string()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.claimed_amount, Uint128::new(500000u128));
+    assert_eq!(res.locked_amount, Uint128::new(1500000u128));
+}
+
+#[test]
+fn claim_for() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    env.block.time = Timestamp::from_seconds(105);
+    let claim_for_msg = ExecuteMsg::ClaimFor {
+        address: "addr0001".to_string(),
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+    };
+
+    // not approved
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("operator", &[]),
+        claim_for_msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::ApproveClaimOperator {
+        operator: "operator".to_string(),
+        expires: Some(Expiration::AtHeight(env.block.height)),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::InvalidExpiration {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::ApproveClaimOperator {
+        operator: "operator".to_string(),
+        expires: Some(Expiration::AtHeight(env.block.height + 10)),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    let msg = ExecuteMsg::RegisterClaimRecipient {
+        recipient: Some("wallet0001".to_string()),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    assert_eq!(
+        from_binary::<ClaimOperatorsResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::ClaimOperators {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        ClaimOperatorsResponse {
+            address: "addr0001".to_string(),
+            recipient: "wallet0001".to_string(),
+            operators: vec![ClaimOperatorData {
+                operator: "operator".to_string(),
+                expires: Expiration::AtHeight(env.block.height + 10),
+            }],
+        }
+    );
+
+    // the registered recipient receives the tokens, not the operator
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("operator", &[]),
+        claim_for_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "wallet0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        })]
+    );
+    assert_eq!(
+        res.attributes[..3],
+        vec![
+            ("action", "claim_for"),
+            ("address", "addr0001"),
+            ("operator", "operator"),
+        ]
+    );
+
+    // own claims also default to the registered recipient
+    env.block.time = Timestamp::from_seconds(106);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "wallet0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(100000u128),
+            }],
+        })]
+    );
+
+    // expired
+    env.block.height += 10;
+    env.block.time = Timestamp::from_seconds(107);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("operator", &[]),
+        claim_for_msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::ClaimOperatorExpired {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // revoked
+    let msg = ExecuteMsg::ApproveClaimOperator {
+        operator: "operator".to_string(),
+        expires: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    let msg = ExecuteMsg::RevokeClaimOperator {
+        operator: "operator".to_string(),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env,
+        mock_info("operator", &[]),
+        claim_for_msg,
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+}
+
+#[test]
+fn claim_for_duplicate_denoms() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    for address in ["addr0001", "addr0002"] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: address.to_string(),
+            vesting_schedule: VestingSchedule::LinearVesting {
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
+                vesting_amount: Uint128::new(1000000u128),
+            },
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::ApproveClaimOperator {
+        operator: "operator".to_string(),
+        expires: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    // the operator cannot pay out the other grants by repeating a denom
+    env.block.time = Timestamp::from_seconds(105);
+    let msg = ExecuteMsg::ClaimFor {
+        address: "addr0001".to_string(),
+        denoms: vec![
+            Denom::Native("uusd".to_string()),
+            Denom::Native("uusd".to_string()),
+        ],
+        grant_ids: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("operator", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(500000u128, "uusd")],
+        })]
+    );
+
+    let res: LockedBalanceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::LockedBalance {
+                denom: Denom::Native("uusd".to_string()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.claimed_amount, Uint128::new(500000u128));
+    assert_eq!(res.locked_amount, Uint128::new(1500000u128));
+}
+
+#[test]
+fn claim_cw20() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    // init env to time 100
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    // valid amount
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1000000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: VestingSchedule::LinearVesting {
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
+                vesting_amount: Uint128::new(1000000u128),
+            },
+            time_unit: None,
+        })
+        .unwrap(),
+    });
+
+    // valid amount
+    let info = mock_info("token0001", &[]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // make time to half claimable
+    env.block.time = Timestamp::from_seconds(105);
+
+    // claim not found denom
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![
+            Denom::Cw20(Addr::unchecked("token0002")),
+            Denom::Cw20(Addr::unchecked("token0001")),
+        ],
+        grant_ids: None,
+        recipient: None,
+    };
+
+    let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res.unwrap_err() {
+        ContractError::DenomNotFound { denom } => assert_eq!(denom, "{\"cw20\":\"token0002\"}"),
+        _ => panic!("should not enter"),
+    }
+
+    // valid claim
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Cw20(Addr::unchecked("token0001"))],
+        grant_ids: None,
+        recipient: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "token0001".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0001".to_string(),
+                amount: Uint128::new(500000u128),
+            })
+            .unwrap(),
+        }),]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "claim"),
+            Attribute::new("address", "addr0001"),
+            Attribute::new("grant_id", "1"),
+            Attribute::new("vesting_denom", "{\"cw20\":\"token0001\"}"),
+            Attribute::new("vesting_amount", "1000000"),
+            Attribute::new("vested_amount", "500000"),
+            Attribute::new("claim_amount", "500000"),
+        ],
+    );
+
+    // query vesting account
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::new(500000),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+
+    // make time to half claimable
+    env.block.time = Timestamp::from_seconds(110);
+
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "token0001".to_string(),
+            funds: vec![],
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0001".to_string(),
+                amount: Uint128::new(500000u128),
+            })
+            .unwrap(),
+        }),]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "claim"),
+            Attribute::new("address", "addr0001"),
+            Attribute::new("grant_id", "1"),
+            Attribute::new("vesting_denom", "{\"cw20\":\"token0001\"}"),
+            Attribute::new("vesting_amount", "1000000"),
+            Attribute::new("vested_amount", "1000000"),
+            Attribute::new("claim_amount", "500000"),
+        ],
+    );
+
+    // query vesting account
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![],
+        }
+    );
+}
+
+#[test]
+fn query_vesting_account() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    // init env to time 100
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    // native vesting
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1000000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: VestingSchedule::LinearVesting {
+                start_time: Uint64::new(100),
+                end_time: Uint64::new(110),
+                vesting_amount: Uint128::new(1000000u128),
+            },
+            time_unit: None,
+        })
+        .unwrap(),
+    });
+
+    // valid amount
+    let info = mock_info("token0001", &[]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // half claimable
+    env.block.time = Timestamp::from_seconds(105);
+
+    // query all entry
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![
+                VestingData {
+                    grant_id: 1,
+                    vesting_denom: Denom::Native("uusd".to_string()),
+                    vesting_amount: Uint128::new(1000000),
+                    vested_amount: Uint128::new(500000),
+                    vesting_schedule: VestingSchedule::LinearVesting {
+                        start_time: Uint64::new(100),
+                        end_time: Uint64::new(110),
+                        vesting_amount: Uint128::new(1000000u128),
+                    },
+                    claimable_amount: Uint128::new(500000),
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                },
+                VestingData {
+                    grant_id: 2,
+                    vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
+                    vesting_amount: Uint128::new(1000000),
+                    vested_amount: Uint128::new(500000),
+                    vesting_schedule: VestingSchedule::LinearVesting {
+                        start_time: Uint64::new(100),
+                        end_time: Uint64::new(110),
+                        vesting_amount: Uint128::new(1000000u128),
+                    },
+                    claimable_amount: Uint128::new(500000),
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                }
+            ],
+        }
+    );
+
+    // query one entry
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: Some(1),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::new(500000),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                claimable_amount: Uint128::new(500000),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            },],
+        }
+    );
+
+    // query one entry after first one
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: Some(1),
+                    limit: Some(1),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 2,
+                vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::new(500000),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                claimable_amount: Uint128::new(500000),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+}
+
+#[test]
+fn query_all_vesting_accounts() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000u128),
+    };
+    for address in ["addr0002", "addr0001", "addr0002"] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: address.to_string(),
+            vesting_schedule: schedule.clone(),
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000u128, "uusd")]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    env.block.time = Timestamp::from_seconds(105);
+    let vesting_data = |grant_id: u64| VestingData {
+        grant_id,
+        vesting_denom: Denom::Native("uusd".to_string()),
+        vesting_amount: Uint128::new(1000u128),
+        vested_amount: Uint128::new(500u128),
+        vesting_schedule: schedule.clone(),
+        claimable_amount: Uint128::new(500u128),
+        time_unit: TimeUnit::Seconds,
+        revoked_at: None,
+        paused_periods: vec![],
+    };
+
+    let res: AllVestingAccountsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::AllVestingAccounts {
+                start_after: None,
+                limit: Some(2),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.vesting_accounts
+            .iter()
+            .map(|a| (a.address.as_str(), a.vesting.grant_id))
+            .collect::<Vec<_>>(),
+        vec![("addr0001", 2), ("addr0002", 1)]
+    );
+    assert_eq!(res.vesting_accounts[0].vesting, vesting_data(2));
+
+    let res: AllVestingAccountsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::AllVestingAccounts {
+                start_after: Some(("addr0002".to_string(), 1)),
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.vesting_accounts.len(), 1);
+    assert_eq!(res.vesting_accounts[0].address, "addr0002");
+    assert_eq!(res.vesting_accounts[0].vesting, vesting_data(3));
+}
+
+#[test]
+fn query_vesting_accounts_by_denom() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000u128),
+    };
+    for (address, denom) in [
+        ("addr0002", "uusd"),
+        ("addr0001", "ukrw"),
+        ("addr0001", "uusd"),
+        ("addr0003", "uusd"),
+    ] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: address.to_string(),
+            vesting_schedule: schedule.clone(),
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000u128, denom)]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    // register cw20 grant for the same address
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+            address: "addr0002".to_string(),
+            vesting_schedule: schedule.clone(),
+            time_unit: None,
+        })
+        .unwrap(),
+    });
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("token0000", &[]), msg).unwrap();
+
+    let query_grants =
+        |deps: Deps, denom: Denom, start_after: Option<(String, u64)>, limit: Option<u32>| {
+            from_binary::<AllVestingAccountsResponse>(
+                &query(
+                    deps,
+                    env.clone(),
+                    QueryMsg::VestingAccountsByDenom {
+                        denom,
+                        start_after,
+                        limit,
+                    },
+                )
+                .unwrap(),
+            )
+            .unwrap()
+            .vesting_accounts
+            .into_iter()
+            .map(|a| (a.address, a.vesting.grant_id))
+            .collect::<Vec<_>>()
+        };
+
+    let uusd = Denom::Native("uusd".to_string());
+    assert_eq!(
+        query_grants(deps.as_ref(), uusd.clone(), None, Some(2)),
+        vec![("addr0001".to_string(), 3), ("addr0002".to_string(), 1)]
+    );
+    assert_eq!(
+        query_grants(deps.as_ref(), uusd, Some(("addr0002".to_string(), 1)), None),
+        vec![("addr0003".to_string(), 4)]
+    );
+    assert_eq!(
+        query_grants(deps.as_ref(), Denom::Native("ukrw".to_string()), None, None),
+        vec![("addr0001".to_string(), 2)]
+    );
+    assert_eq!(
+        query_grants(
+            deps.as_ref(),
+            Denom::Cw20(Addr::unchecked("token0000")),
+            None,
+            None
+        ),
+        vec![("addr0002".to_string(), 5)]
+    );
+
+    // the index follows removals
+    let msg = ExecuteMsg::DeregisterVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 2,
+        vested_token_recipient: None,
+        left_vesting_token_recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        query_grants(deps.as_ref(), Denom::Native("ukrw".to_string()), None, None),
+        vec![]
+    );
+}
+
+#[test]
+fn query_locked_balance() {
+    let mut deps = mock_querier::mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000u128),
+    };
+    for address in ["addr0001", "addr0002"] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: address.to_string(),
+            vesting_schedule: schedule.clone(),
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000u128, "uusd")]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "addr0000".to_string(),
+        amount: Uint128::new(1000u128),
+        msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: schedule,
+            time_unit: None,
+        })
+        .unwrap(),
+    });
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("token0000", &[]), msg).unwrap();
+
+    env.block.time = Timestamp::from_seconds(105);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    // 500 vested to the vesting account and 500 returned to the master
+    let msg = ExecuteMsg::DeregisterVestingAccount {
+        address: "addr0002".to_string(),
+        grant_id: 2,
+        vested_token_recipient: None,
+        left_vesting_token_recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(600u128, "uusd")]);
+    deps.querier
+        .with_token_balances(&[("token0000", &[(MOCK_CONTRACT_ADDR, Uint128::new(1000u128))])]);
+
+    assert_eq!(
+        from_binary::<LockedBalanceResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::LockedBalance {
+                    denom: Denom::Native("uusd".to_string()),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        LockedBalanceResponse {
+            vesting_denom: Denom::Native("uusd".to_string()),
+            deposited_amount: Uint128::new(2000u128),
+            claimed_amount: Uint128::new(1000u128),
+            locked_amount: Uint128::new(500u128),
+            delegated_amount: Uint128::zero(),
+            unbonding_amount: Uint128::zero(),
+            balance: Uint128::new(600u128),
+        }
+    );
+
+    assert_eq!(
+        from_binary::<LockedBalanceResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::LockedBalance {
+                    denom: Denom::Cw20(Addr::unchecked("token0000")),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        LockedBalanceResponse {
+            vesting_denom: Denom::Cw20(Addr::unchecked("token0000")),
+            deposited_amount: Uint128::new(1000u128),
+            claimed_amount: Uint128::zero(),
+            locked_amount: Uint128::new(1000u128),
+            delegated_amount: Uint128::zero(),
+            unbonding_amount: Uint128::zero(),
+            balance: Uint128::new(1000u128),
+        }
+    );
+
+    // unknown denom
+    assert_eq!(
+        from_binary::<LockedBalanceResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::LockedBalance {
+                    denom: Denom::Native("ukrw".to_string()),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        LockedBalanceResponse {
+            vesting_denom: Denom::Native("ukrw".to_string()),
+            deposited_amount: Uint128::zero(),
+            claimed_amount: Uint128::zero(),
+            locked_amount: Uint128::zero(),
+            delegated_amount: Uint128::zero(),
+            unbonding_amount: Uint128::zero(),
+            balance: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn sweep_surplus() {
+    let mut deps = mock_querier::mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000u128),
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // no surplus
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000u128, "uusd")]);
+    let msg = ExecuteMsg::SweepSurplus {
+        denom: Denom::Native("uusd".to_string()),
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::NoSurplus { denom } => assert_eq!(denom, "{\"native\":\"uusd\"}"),
+        _ => panic!("should not enter"),
+    }
+
+    // coins sent by mistake
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1500u128, "uusd")]);
+    let msg = ExecuteMsg::SweepSurplus {
+        denom: Denom::Native("uusd".to_string()),
+        recipient: Some("addr0002".to_string()),
+    };
+
+    // unauthorized
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0002".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500u128),
+            }],
+        })]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "sweep_surplus"),
+            ("vesting_denom", "{\"native\":\"uusd\"}"),
+            ("recipient", "addr0002"),
+            ("locked_amount", "1000"),
+            ("surplus_amount", "500"),
+        ]
+    );
+
+    // cw20 tokens transferred without the hook
+    deps.querier
+        .with_token_balances(&[("token0000", &[(MOCK_CONTRACT_ADDR, Uint128::new(300u128))])]);
+    let msg = ExecuteMsg::SweepSurplus {
+        denom: Denom::Cw20(Addr::unchecked("token0000")),
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(WasmMsg::Execute {
+            contract_addr: "token0000".to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: "addr0000".to_string(),
+                amount: Uint128::new(300u128),
+            })
+            .unwrap(),
+            funds: vec![],
+        })]
+    );
+}
+
+#[test]
+fn register_cliff_vesting_account_with_native_token() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    // zero amount vesting token
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::CliffVesting {
+            schedules: vec![
+                CliffSchedule {
+                    release_time: Uint64::new(105),
+                    release_amount: Uint128::zero(),
+                },
+                CliffSchedule {
+                    release_time: Uint64::new(110),
+                    release_amount: Uint128::zero(),
+                },
+            ],
+        },
+        time_unit: None,
+    };
+
+    // invalid zero amount
+    let info = mock_info("addr0000", &[Coin::new(0u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg);
+    match res.unwrap_err() {
+        ContractError::ZeroDepositAmount {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // normal amount vesting token
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::CliffVesting {
+            schedules: vec![
+                CliffSchedule {
+                    release_time: Uint64::new(105),
                     release_amount: Uint128::new(500000u128),
                 },
-                CliffSchedule {
-                    release_time: "110".to_string(),
-                    release_amount: Uint128::new(500000u128),
+                CliffSchedule {
+                    release_time: Uint64::new(110),
+                    release_amount: Uint128::new(500000u128),
+                },
+            ],
+        },
+        time_unit: None,
+    };
+
+    // invalid amount
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    match res.unwrap_err() {
+        ContractError::InvalidFunds {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // invalid amount
+    let info = mock_info(
+        "addr0000",
+        &[Coin::new(100u128, "uusd"), Coin::new(10u128, "ukrw")],
+    );
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    match res.unwrap_err() {
+        ContractError::InvalidFunds {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // invalid amount
+    let info = mock_info("addr0000", &[Coin::new(10u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    match res.unwrap_err() {
+        ContractError::DepositAmountMismatch { .. } => {}
+        _ => panic!("should not enter"),
+    }
+
+    // valid amount
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res: Response = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "register_vesting_account"),
+            ("address", "addr0001"),
+            ("grant_id", "1"),
+            ("vesting_denom", "{\"native\":\"uusd\"}"),
+            ("vesting_amount", "1000000"),
+        ]
+    );
+
+    // query vesting account
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::zero(),
+                vesting_schedule: VestingSchedule::CliffVesting {
+                    schedules: vec![
+                        CliffSchedule {
+                            release_time: Uint64::new(105),
+                            release_amount: Uint128::new(500000u128),
+                        },
+                        CliffSchedule {
+                            release_time: Uint64::new(110),
+                            release_amount: Uint128::new(500000u128),
+                        },
+                    ],
+                },
+                claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+}
+
+#[test]
+fn register_hybrid_vesting_account_with_native_token() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    // cliff amount exceeds vesting amount
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::HybridVesting {
+            cliff_time: Uint64::new(105),
+            cliff_amount: Uint128::new(2000000u128),
+            end_time: Uint64::new(115),
+            vesting_interval: None,
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res.unwrap_err() {
+        ContractError::InvalidCliffAmount {
+            cliff_amount,
+            vesting_amount,
+        } => {
+            assert_eq!(cliff_amount, Uint128::new(2000000u128));
+            assert_eq!(vesting_amount, Uint128::new(1000000u128));
+        }
+        _ => panic!("should not enter"),
+    }
+
+    // interval does not divide the vesting period
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::HybridVesting {
+            cliff_time: Uint64::new(105),
+            cliff_amount: Uint128::new(250000u128),
+            end_time: Uint64::new(115),
+            vesting_interval: Some(Uint64::new(3)),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res.unwrap_err() {
+        ContractError::InvalidVestingInterval {
+            vesting_period,
+            vesting_interval,
+        } => {
+            assert_eq!(vesting_period, 10);
+            assert_eq!(vesting_interval, 3);
+        }
+        _ => panic!("should not enter"),
+    }
+
+    // valid hybrid vesting
+    let vesting_schedule = VestingSchedule::HybridVesting {
+        cliff_time: Uint64::new(105),
+        cliff_amount: Uint128::new(250000u128),
+        end_time: Uint64::new(115),
+        vesting_interval: Some(Uint64::new(5)),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: vesting_schedule.clone(),
+        time_unit: None,
+    };
+
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // cliff amount is released at cliff time
+    env.block.time = Timestamp::from_seconds(105);
+
+    // query vesting account
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::new(250000),
+                vesting_schedule,
+                claimable_amount: Uint128::new(250000),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+}
+
+#[test]
+fn register_height_vesting_account() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.height = 1000;
+    env.block.time = Timestamp::from_seconds(100);
+
+    // heights are validated against the block height
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(999),
+            end_time: Uint64::new(1010),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: Some(TimeUnit::Height),
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res.unwrap_err() {
+        ContractError::TimeBeforeBlockTime {
+            name,
+            time,
+            block_time,
+        } => {
+            assert_eq!(name, "start_time");
+            assert_eq!(time, 999);
+            assert_eq!(block_time, 1000);
+        }
+        _ => panic!("should not enter"),
+    }
+
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(1000),
+        end_time: Uint64::new(1010),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: vesting_schedule.clone(),
+        time_unit: Some(TimeUnit::Height),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // vested by the block height regardless of the block time
+    env.block.height = 1005;
+    env.block.time = Timestamp::from_seconds(10000);
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000u128),
+                vested_amount: Uint128::new(500000u128),
+                vesting_schedule,
+                time_unit: TimeUnit::Height,
+                claimable_amount: Uint128::new(500000u128),
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env, mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        })]
+    );
+}
+
+#[test]
+fn multiple_grants_per_denom() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    // init env to time 100
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    // initial grant
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // refresh grant in the same denom
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(120),
+            vesting_amount: Uint128::new(2000000u128),
+        },
+        time_unit: None,
+    };
+
+    let info = mock_info("addr0000", &[Coin::new(2000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "register_vesting_account"),
+            ("address", "addr0001"),
+            ("grant_id", "2"),
+            ("vesting_denom", "{\"native\":\"uusd\"}"),
+            ("vesting_amount", "2000000"),
+        ]
+    );
+
+    env.block.time = Timestamp::from_seconds(105);
+
+    // claim only the refresh grant
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: Some(vec![2]),
+        recipient: None,
+    };
+
+    let info = mock_info("addr0001", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        }),]
+    );
+
+    // claim not found grant
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: Some(vec![1, 3]),
+        recipient: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+    match res.unwrap_err() {
+        ContractError::VestingNotFound { grant_id } => assert_eq!(grant_id, 3),
+        _ => panic!("should not enter"),
+    }
+
+    // claim all grants of the denom
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(500000u128),
+            }],
+        }),]
+    );
+    assert_eq!(
+        res.attributes,
+        vec![
+            Attribute::new("action", "claim"),
+            Attribute::new("address", "addr0001"),
+            Attribute::new("grant_id", "1"),
+            Attribute::new("vesting_denom", "{\"native\":\"uusd\"}"),
+            Attribute::new("vesting_amount", "1000000"),
+            Attribute::new("vested_amount", "500000"),
+            Attribute::new("claim_amount", "500000"),
+        ],
+    );
+
+    // deregister the refresh grant only
+    let msg = ExecuteMsg::DeregisterVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 2,
+        vested_token_recipient: None,
+        left_vesting_token_recipient: None,
+    };
+
+    let info = mock_info("addr0000", &[]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(1500000u128),
+            }],
+        }),]
+    );
+
+    // only the initial grant is left
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![VestingData {
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000),
+                vested_amount: Uint128::new(500000),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                claimable_amount: Uint128::zero(),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+            }],
+        }
+    );
+}
+
+#[test]
+fn migrate_from_v1() {
+    let mut deps = mock_dependencies(&[]);
+
+    // contract deployed before grant ids and cw2
+    MASTER_ADDRESS_V4
+        .save(deps.as_mut().storage, &Addr::unchecked("addr0000"))
+        .unwrap();
+
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    for (address, denom_key, denom) in [
+        ("addr0001", "native-uusd", Denom::Native("uusd".to_string())),
+        (
+            "addr0001",
+            "cw20-token0001",
+            Denom::Cw20(Addr::unchecked("token0001")),
+        ),
+        ("addr0002", "native-uusd", Denom::Native("uusd".to_string())),
+    ] {
+        VESTING_ACCOUNTS_V1
+            .save(
+                deps.as_mut().storage,
+                (address, denom_key),
+                &VestingAccountV1 {
+                    address: address.to_string(),
+                    vesting_denom: denom,
+                    vesting_amount: Uint128::new(1000000u128),
+                    vesting_schedule: vesting_schedule.clone(),
+                    claimed_amount: Uint128::new(100000u128),
+                },
+            )
+            .unwrap();
+    }
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV1 {}).unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "migrate"),
+            ("contract_name", "crates.io:token-vesting"),
+            ("contract_version", env!("CARGO_PKG_VERSION")),
+        ]
+    );
+
+    let contract_version = get_contract_version(deps.as_ref().storage).unwrap();
+    assert_eq!(contract_version.contract, "crates.io:token-vesting");
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(105);
+
+    // grants are ordered by the former (address, denom_key) keys
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        VestingAccountResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![
+                VestingData {
+                    grant_id: 1,
+                    vesting_denom: Denom::Cw20(Addr::unchecked("token0001")),
+                    vesting_amount: Uint128::new(1000000),
+                    vested_amount: Uint128::new(500000),
+                    vesting_schedule: vesting_schedule.clone(),
+                    claimable_amount: Uint128::new(400000),
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                },
+                VestingData {
+                    grant_id: 2,
+                    vesting_denom: Denom::Native("uusd".to_string()),
+                    vesting_amount: Uint128::new(1000000),
+                    vested_amount: Uint128::new(500000),
+                    vesting_schedule: vesting_schedule.clone(),
+                    claimable_amount: Uint128::new(400000),
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                },
+            ],
+        }
+    );
+
+    // migrated grants are usable, and new grants continue the sequence
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0002".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(105),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    assert_eq!(res.attributes[2], ("grant_id", "4"));
+
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: Some(vec![3]),
+        recipient: None,
+    };
+    let info = mock_info("addr0002", &[]);
+    let res = execute(deps.as_mut(), env, info, msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0002".to_string(),
+            amount: vec![Coin {
+                denom: "uusd".to_string(),
+                amount: Uint128::new(400000u128),
+            }],
+        }),]
+    );
+}
+
+#[test]
+fn migrate_from_v2() {
+    let mut deps = mock_dependencies(&[]);
+    set_contract_version(deps.as_mut().storage, "crates.io:token-vesting", "0.1.0").unwrap();
+
+    MASTER_ADDRESS_V4
+        .save(deps.as_mut().storage, &Addr::unchecked("addr0000"))
+        .unwrap();
+
+    // grants stored without the denom index
+    let vesting_accounts_v2: Map<(&Addr, U64Key), VestingAccount> = Map::new("vesting_accounts");
+    let address = Addr::unchecked("addr0001");
+    vesting_accounts_v2
+        .save(
+            deps.as_mut().storage,
+            (&address, U64Key::new(1)),
+            &VestingAccount {
+                address: address.clone(),
+                grant_id: 1,
+                vesting_denom: Denom::Native("uusd".to_string()),
+                vesting_amount: Uint128::new(1000000u128),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(110),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                claimed_amount: Uint128::new(250000u128),
+                time_unit: TimeUnit::Seconds,
+                revoked_at: None,
+                paused_periods: vec![],
+                delegated_amount: Uint128::zero(),
+                unbondings: vec![],
+                rewards: Uint128::zero(),
+                slashed_amount: Uint128::zero(),
+            },
+        )
+        .unwrap();
+
+    let query_msg = QueryMsg::VestingAccountsByDenom {
+        denom: Denom::Native("uusd".to_string()),
+        start_after: None,
+        limit: None,
+    };
+    let res: AllVestingAccountsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_msg.clone()).unwrap()).unwrap();
+    assert_eq!(res.vesting_accounts, vec![]);
+
+    let _ = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV2 {}).unwrap();
+
+    let res: AllVestingAccountsResponse =
+        from_binary(&query(deps.as_ref(), mock_env(), query_msg).unwrap()).unwrap();
+    assert_eq!(res.vesting_accounts.len(), 1);
+    assert_eq!(res.vesting_accounts[0].address, "addr0001");
+    assert_eq!(res.vesting_accounts[0].vesting.grant_id, 1);
+
+    let res: LockedBalanceResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::LockedBalance {
+                denom: Denom::Native("uusd".to_string()),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.deposited_amount, Uint128::new(1000000u128));
+    assert_eq!(res.claimed_amount, Uint128::new(250000u128));
+    assert_eq!(res.locked_amount, Uint128::new(750000u128));
+
+    let res: VotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::VotingPower {
+                address: "addr0001".to_string(),
+                denom: Some(Denom::Native("uusd".to_string())),
+                height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.voting_powers,
+        vec![DenomVotingPower {
+            denom: Denom::Native("uusd".to_string()),
+            voting_power: Uint128::new(750000u128),
+        }]
+    );
+
+    // rebuilding the voting power again overwrites it, including stale entries
+    let mut env = mock_env();
+    env.block.height += 1;
+    VOTING_POWER
+        .save(
+            deps.as_mut().storage,
+            (&Addr::unchecked("addr0002"), "native-uusd"),
+            &Uint128::new(5u128),
+            env.block.height,
+        )
+        .unwrap();
+    migrations::migrate_from_v3(deps.as_mut().storage, env.block.height).unwrap();
+
+    env.block.height += 1;
+    for (address, voting_power) in [("addr0001", 750000u128), ("addr0002", 0u128)] {
+        let res: VotingPowerResponse = from_binary(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VotingPower {
+                    address: address.to_string(),
+                    denom: Some(Denom::Native("uusd".to_string())),
+                    height: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            res.voting_powers,
+            vec![DenomVotingPower {
+                denom: Denom::Native("uusd".to_string()),
+                voting_power: Uint128::new(voting_power),
+            }]
+        );
+    }
+    let res: TotalVotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env,
+            QueryMsg::TotalVotingPower {
+                denom: Some(Denom::Native("uusd".to_string())),
+                height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.voting_powers,
+        vec![DenomVotingPower {
+            denom: Denom::Native("uusd".to_string()),
+            voting_power: Uint128::new(750000u128),
+        }]
+    );
+
+    let res: RolesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Roles {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.roles, vec![Role::Admin, Role::Registrar, Role::Revoker]);
+}
+
+#[test]
+fn migrate_from_v3() {
+    let mut deps = mock_dependencies(&[]);
+    set_contract_version(deps.as_mut().storage, "crates.io:token-vesting", "0.1.0").unwrap();
+
+    MASTER_ADDRESS_V4
+        .save(deps.as_mut().storage, &Addr::unchecked("addr0000"))
+        .unwrap();
+
+    // grants stored before the voting power, one of them slashed
+    for (address, grant_id, amount, claimed_amount, slashed_amount) in [
+        ("addr0001", 1u64, 1000000u128, 250000u128, 100000u128),
+        ("addr0002", 2u64, 300000u128, 0u128, 0u128),
+    ] {
+        let address = Addr::unchecked(address);
+        vesting_accounts()
+            .save(
+                deps.as_mut().storage,
+                (&address, U64Key::new(grant_id)),
+                &VestingAccount {
+                    address: address.clone(),
+                    grant_id,
+                    vesting_denom: Denom::Native("uusd".to_string()),
+                    vesting_amount: Uint128::new(amount),
+                    vesting_schedule: VestingSchedule::LinearVesting {
+                        start_time: Uint64::new(100),
+                        end_time: Uint64::new(110),
+                        vesting_amount: Uint128::new(amount),
+                    },
+                    claimed_amount: Uint128::new(claimed_amount),
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                    delegated_amount: Uint128::zero(),
+                    unbondings: vec![],
+                    rewards: Uint128::zero(),
+                    slashed_amount: Uint128::new(slashed_amount),
+                },
+            )
+            .unwrap();
+    }
+
+    let mut env = mock_env();
+    env.block.height = 200;
+    let _ = migrate(deps.as_mut(), env, MigrateMsg::FromV3 {}).unwrap();
+
+    // the slashed amount is not counted, and the snapshots start at the migration height
+    let voting_power = |deps: Deps, address: &str, height: Option<u64>| {
+        from_binary::<VotingPowerResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::VotingPower {
+                    address: address.to_string(),
+                    denom: Some(Denom::Native("uusd".to_string())),
+                    height,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .voting_powers[0]
+            .voting_power
+    };
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", Some(200)),
+        Uint128::zero()
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", Some(201)),
+        Uint128::new(650000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0002", None),
+        Uint128::new(300000u128)
+    );
+
+    let res: TotalVotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::TotalVotingPower {
+                denom: Some(Denom::Native("uusd".to_string())),
+                height: Some(201),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.voting_powers,
+        vec![DenomVotingPower {
+            denom: Denom::Native("uusd".to_string()),
+            voting_power: Uint128::new(950000u128),
+        }]
+    );
+
+    let res: RolesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Roles {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.roles, vec![Role::Admin, Role::Registrar, Role::Revoker]);
+    assert_eq!(
+        MASTER_ADDRESS_V4.may_load(deps.as_ref().storage).unwrap(),
+        None
+    );
+}
+
+#[test]
+fn migrate_from_v4() {
+    let mut deps = mock_dependencies(&[]);
+    set_contract_version(deps.as_mut().storage, "crates.io:token-vesting", "0.1.0").unwrap();
+
+    let env = mock_env();
+    MASTER_ADDRESS_V4
+        .save(deps.as_mut().storage, &Addr::unchecked("addr0000"))
+        .unwrap();
+    PENDING_MASTER_ADDRESS_V4
+        .save(
+            deps.as_mut().storage,
+            &PendingMasterAddressV4 {
+                master_address: Addr::unchecked("addr0009"),
+                expires: Expiration::AtHeight(env.block.height + 10),
+            },
+        )
+        .unwrap();
+
+    // the voting power is already stored in layout v4
+    VOTING_POWER
+        .save(
+            deps.as_mut().storage,
+            (&Addr::unchecked("addr0001"), "native-uusd"),
+            &Uint128::new(750000u128),
+            100,
+        )
+        .unwrap();
+
+    let _ = migrate(deps.as_mut(), env.clone(), MigrateMsg::FromV4 {}).unwrap();
+
+    // the voting power and its snapshots are kept
+    let res: VotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::VotingPower {
+                address: "addr0001".to_string(),
+                denom: None,
+                height: Some(101),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.voting_powers,
+        vec![DenomVotingPower {
+            denom: Denom::Native("uusd".to_string()),
+            voting_power: Uint128::new(750000u128),
+        }]
+    );
+
+    // the master address holds every role, and its pending proposal is kept
+    let res: RolesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::Roles {
+                address: "addr0000".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.roles, vec![Role::Admin, Role::Registrar, Role::Revoker]);
+    assert_eq!(
+        MASTER_ADDRESS_V4.may_load(deps.as_ref().storage).unwrap(),
+        None
+    );
+    assert_eq!(
+        PENDING_MASTER_ADDRESS_V4
+            .may_load(deps.as_ref().storage)
+            .unwrap(),
+        None
+    );
+
+    let res: PendingAdminsResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::PendingAdmins {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.pending_admins,
+        vec![PendingAdminData {
+            address: "addr0009".to_string(),
+            expires: Expiration::AtHeight(env.block.height + 10),
+        }]
+    );
+
+    let _ = execute(
+        deps.as_mut(),
+        env,
+        mock_info("addr0009", &[]),
+        ExecuteMsg::AcceptAdmin {},
+    )
+    .unwrap();
+    let res: RolesResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::Roles {
+                address: "addr0009".to_string(),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.roles, vec![Role::Admin]);
+}
+
+#[test]
+fn migrate_from_other_contract() {
+    let mut deps = mock_dependencies(&[]);
+    set_contract_version(deps.as_mut().storage, "crates.io:cw20-base", "0.9.1").unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV3 {});
+    match res.unwrap_err() {
+        ContractError::CannotMigrate { previous_contract } => {
+            assert_eq!(previous_contract, "crates.io:cw20-base")
+        }
+        _ => panic!("should not enter"),
+    }
+}
+
+#[test]
+fn migrate_from_wrong_layout() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    // the stored layout is the current one
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV1 {});
+    match res.unwrap_err() {
+        ContractError::LayoutMismatch { stored, requested } => {
+            assert_eq!(stored, 5);
+            assert_eq!(requested, 1);
+        }
+        _ => panic!("should not enter"),
+    }
+
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV5 {}).unwrap();
+
+    // cw2 info without the layout is v2 or later
+    let mut deps = mock_dependencies(&[]);
+    set_contract_version(deps.as_mut().storage, "crates.io:token-vesting", "0.1.0").unwrap();
+    MASTER_ADDRESS_V4
+        .save(deps.as_mut().storage, &Addr::unchecked("addr0000"))
+        .unwrap();
+
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV1 {});
+    match res.unwrap_err() {
+        ContractError::InvalidLayout { requested } => assert_eq!(requested, 1),
+        _ => panic!("should not enter"),
+    }
+
+    // the same path cannot be run twice
+    let _res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV2 {}).unwrap();
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV2 {});
+    match res.unwrap_err() {
+        ContractError::LayoutMismatch { stored, requested } => {
+            assert_eq!(stored, 5);
+            assert_eq!(requested, 2);
+        }
+        _ => panic!("should not enter"),
+    }
+
+    // no contract info is v1 or v2
+    let mut deps = mock_dependencies(&[]);
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg::FromV4 {});
+    match res.unwrap_err() {
+        ContractError::InvalidLayout { requested } => assert_eq!(requested, 4),
+        _ => panic!("should not enter"),
+    }
+}
+
+#[test]
+fn reject_invalid_addresses() {
+    fn assert_invalid_address(res: Result<Response, ContractError>) {
+        match res.unwrap_err() {
+            ContractError::Std(StdError::GenericErr { msg, .. }) => {
+                assert_eq!(msg, "Invalid input: human address too short")
+            }
+            _ => panic!("should not enter"),
+        }
+    }
+
+    let mut deps = mock_dependencies(&[]);
+
+    // invalid master address
+    assert_invalid_address(instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: Some("a".to_string()),
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    ));
+
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+    let master_info = mock_info("addr0000", &[]);
+
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        master_info.clone(),
+        ExecuteMsg::GrantRole {
+            role: Role::Registrar,
+            address: "a".to_string(),
+        },
+    ));
+
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        master_info.clone(),
+        ExecuteMsg::ProposeAdmin {
+            address: "a".to_string(),
+            expires: None,
+        },
+    ));
+
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        master_info.clone(),
+        ExecuteMsg::CancelAdminProposal {
+            address: "a".to_string(),
+        },
+    ));
+
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        master_info.clone(),
+        ExecuteMsg::RevokeRole {
+            role: Role::Admin,
+            address: "a".to_string(),
+        },
+    ));
+
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+
+    // invalid vesting account address
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]),
+        ExecuteMsg::RegisterVestingAccount {
+            address: "a".to_string(),
+            vesting_schedule: vesting_schedule.clone(),
+            time_unit: None,
+        },
+    ));
+
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("token0000", &[]),
+        ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "addr0000".to_string(),
+            amount: Uint128::new(1000000u128),
+            msg: to_binary(&Cw20HookMsg::RegisterVestingAccount {
+                address: "a".to_string(),
+                vesting_schedule: vesting_schedule.clone(),
+                time_unit: None,
+            })
+            .unwrap(),
+        }),
+    ));
+
+    let _res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]),
+        ExecuteMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule,
+            time_unit: None,
+        },
+    )
+    .unwrap();
+
+    env.block.time = Timestamp::from_seconds(105);
+
+    // invalid claim recipient
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        ExecuteMsg::Claim {
+            denoms: vec![Denom::Native("uusd".to_string())],
+            grant_ids: None,
+            recipient: Some("a".to_string()),
+        },
+    ));
+
+    // invalid deregister recipients
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        master_info.clone(),
+        ExecuteMsg::DeregisterVestingAccount {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+            vested_token_recipient: Some("a".to_string()),
+            left_vesting_token_recipient: None,
+        },
+    ));
+
+    assert_invalid_address(execute(
+        deps.as_mut(),
+        env.clone(),
+        master_info,
+        ExecuteMsg::DeregisterVestingAccount {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+            vested_token_recipient: None,
+            left_vesting_token_recipient: Some("a".to_string()),
+        },
+    ));
+
+    // invalid query address
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::VestingAccount {
+            address: "a".to_string(),
+            start_after: None,
+            limit: None,
+        },
+    );
+    match res.unwrap_err() {
+        StdError::GenericErr { msg, .. } => {
+            assert_eq!(msg, "Invalid input: human address too short")
+        }
+        _ => panic!("should not enter"),
+    }
+}
+
+#[test]
+fn pause_vesting() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    // grant 1 vests linearly, grant 2 vests 100000 every 10 seconds
+    let msg = ExecuteMsg::RegisterVestingAccounts {
+        vesting_accounts: vec![
+            NewVestingAccount {
+                address: "addr0001".to_string(),
+                vesting_schedule: VestingSchedule::LinearVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(200),
+                    vesting_amount: Uint128::new(1000000u128),
+                },
+                time_unit: None,
+            },
+            NewVestingAccount {
+                address: "addr0001".to_string(),
+                vesting_schedule: VestingSchedule::PeriodicVesting {
+                    start_time: Uint64::new(100),
+                    end_time: Uint64::new(190),
+                    vesting_interval: Uint64::new(10),
+                    amount: Uint128::new(100000u128),
+                },
+                time_unit: None,
+            },
+        ],
+    };
+    let info = mock_info("addr0000", &[Coin::new(2000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let vested_amounts = |deps: Deps, env: Env| -> Vec<Uint128> {
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps,
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .vestings
+        .into_iter()
+        .map(|v| v.vested_amount)
+        .collect()
+    };
+    let pause = |grant_id: u64| ExecuteMsg::PauseVesting {
+        address: "addr0001".to_string(),
+        grant_id,
+    };
+    let resume = |grant_id: u64| ExecuteMsg::ResumeVesting {
+        address: "addr0001".to_string(),
+        grant_id,
+    };
+
+    env.block.time = Timestamp::from_seconds(110);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        pause(1),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        resume(1),
+    );
+    match res.unwrap_err() {
+        ContractError::VestingNotPaused { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+
+    // first window: 110 ~ 120
+    for grant_id in [1, 2] {
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr0000", &[]),
+            pause(grant_id),
+        )
+        .unwrap();
+        assert_eq!(
+            res.attributes,
+            vec![
+                ("action", "pause_vesting"),
+                ("address", "addr0001"),
+                ("grant_id", &grant_id.to_string()),
+                ("paused_at", "110"),
+            ]
+        );
+    }
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        pause(1),
+    );
+    match res.unwrap_err() {
+        ContractError::VestingPaused { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+
+    // nothing is vested while paused
+    env.block.time = Timestamp::from_seconds(118);
+    assert_eq!(
+        vested_amounts(deps.as_ref(), env.clone()),
+        vec![Uint128::new(100000u128), Uint128::new(200000u128)]
+    );
+
+    env.block.time = Timestamp::from_seconds(120);
+    for grant_id in [1, 2] {
+        let _ = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr0000", &[]),
+            resume(grant_id),
+        )
+        .unwrap();
+    }
+
+    // second window: 150 ~ 165
+    env.block.time = Timestamp::from_seconds(150);
+    for grant_id in [1, 2] {
+        let _ = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr0000", &[]),
+            pause(grant_id),
+        )
+        .unwrap();
+    }
+    assert_eq!(
+        vested_amounts(deps.as_ref(), env.clone()),
+        vec![Uint128::new(400000u128), Uint128::new(500000u128)]
+    );
+
+    env.block.time = Timestamp::from_seconds(165);
+    for grant_id in [1, 2] {
+        let _ = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("addr0000", &[]),
+            resume(grant_id),
+        )
+        .unwrap();
+    }
+
+    // the schedules are postponed by 25 seconds
+    env.block.time = Timestamp::from_seconds(200);
+    assert_eq!(
+        vested_amounts(deps.as_ref(), env.clone()),
+        vec![Uint128::new(750000u128), Uint128::new(800000u128)]
+    );
+
+    env.block.time = Timestamp::from_seconds(215);
+    assert_eq!(
+        vested_amounts(deps.as_ref(), env.clone()),
+        vec![Uint128::new(900000u128), Uint128::new(1000000u128)]
+    );
+
+    env.block.time = Timestamp::from_seconds(225);
+    assert_eq!(
+        vested_amounts(deps.as_ref(), env.clone()),
+        vec![Uint128::new(1000000u128), Uint128::new(1000000u128)]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::VestingAccount {
+            address: "addr0001".to_string(),
+            start_after: None,
+            limit: Some(1),
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(&res)
+            .unwrap()
+            .vestings[0]
+            .paused_periods,
+        vec![
+            PausedPeriod {
+                start: 110,
+                end: Some(120),
+            },
+            PausedPeriod {
+                start: 150,
+                end: Some(165),
+            },
+        ]
+    );
+}
+
+#[test]
+fn top_up_paused_vesting() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::CliffVesting {
+            schedules: vec![CliffSchedule {
+                release_time: Uint64::new(150),
+                release_amount: Uint128::new(1000000u128),
+            }],
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    // paused for 20 seconds, so the schedule is at 120 by the block time 140
+    env.block.time = Timestamp::from_seconds(110);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::PauseVesting {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+        },
+    )
+    .unwrap();
+    env.block.time = Timestamp::from_seconds(130);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::ResumeVesting {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+        },
+    )
+    .unwrap();
+
+    env.block.time = Timestamp::from_seconds(140);
+    let top_up = |release_time: u64| ExecuteMsg::TopUpVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        schedules: Some(vec![CliffSchedule {
+            release_time: Uint64::new(release_time),
+            release_amount: Uint128::new(500000u128),
+        }]),
+    };
+    let info = mock_info("addr0000", &[Coin::new(500000u128, "uusd")]);
+
+    // already passed on the schedule
+    let res = execute(deps.as_mut(), env.clone(), info.clone(), top_up(115));
+    match res.unwrap_err() {
+        ContractError::TimeBeforeBlockTime {
+            name,
+            time,
+            block_time,
+        } => {
+            assert_eq!(name, "release_time");
+            assert_eq!(time, 115);
+            assert_eq!(block_time, 120);
+        }
+        _ => panic!("should not enter"),
+    }
+
+    // before the block time, but still ahead on the schedule
+    let _ = execute(deps.as_mut(), env.clone(), info, top_up(125)).unwrap();
+
+    let vested_amount = |env: Env| -> Uint128 {
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .vestings[0]
+            .vested_amount
+    };
+    assert_eq!(vested_amount(env.clone()), Uint128::zero());
+
+    env.block.time = Timestamp::from_seconds(145);
+    assert_eq!(vested_amount(env.clone()), Uint128::new(500000u128));
+
+    env.block.time = Timestamp::from_seconds(170);
+    assert_eq!(vested_amount(env), Uint128::new(1500000u128));
+}
+
+#[test]
+fn global_pause() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: Some("guardian".to_string()),
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let register_msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(110),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let deposit = [Coin::new(1000000u128, "uusd")];
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &deposit),
+        register_msg.clone(),
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        ExecuteMsg::SetPaused {
+            claims: Some(true),
+            registrations: None,
+            deregistrations: None,
+        },
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // the guardian halts claims only
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::SetPaused {
+            claims: Some(true),
+            registrations: None,
+            deregistrations: None,
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        res.attributes,
+        vec![
+            ("action", "set_paused"),
+            ("claims", "true"),
+            ("registrations", "false"),
+            ("deregistrations", "false"),
+        ]
+    );
+    assert_eq!(
+        from_binary::<ConfigResponse>(
+            &query(deps.as_ref(), env.clone(), QueryMsg::Config {}).unwrap()
+        )
+        .unwrap(),
+        ConfigResponse {
+            require_transfer_approval: false,
+            guardian: Some("guardian".to_string()),
+            paused: PausedOperations {
+                claims: true,
+                registrations: false,
+                deregistrations: false,
+            },
+            unbonding_period: 1814400,
+        }
+    );
+
+    let claim_msg = ExecuteMsg::ClaimAll { recipient: None };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::ClaimsPaused {} => {}
+        _ => panic!("should not enter"),
+    }
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &deposit),
+        register_msg.clone(),
+    )
+    .unwrap();
+
+    env.block.time = Timestamp::from_seconds(105);
+
+    // the guardian cannot lift the pause
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::SetPaused {
+            claims: Some(false),
+            registrations: Some(true),
+            deregistrations: Some(true),
+        },
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::SetPaused {
+            claims: None,
+            registrations: Some(true),
+            deregistrations: Some(true),
+        },
+    )
+    .unwrap();
+
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &deposit),
+        register_msg,
+    );
+    match res.unwrap_err() {
+        ContractError::RegistrationsPaused {} => {}
+        _ => panic!("should not enter"),
+    }
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::DeregisterVestingAccount {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+            vested_token_recipient: None,
+            left_vesting_token_recipient: None,
+        },
+    );
+    match res.unwrap_err() {
+        ContractError::DeregistrationsPaused {} => {}
+        _ => panic!("should not enter"),
+    }
+
+    // the master resumes claims, the other operations stay halted
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::SetPaused {
+            claims: Some(false),
+            registrations: None,
+            deregistrations: None,
+        },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg,
+    )
+    .unwrap();
+    let send = SubMsg::new(BankMsg::Send {
+        to_address: "addr0001".to_string(),
+        amount: vec![Coin {
+            denom: "uusd".to_string(),
+            amount: Uint128::new(500000u128),
+        }],
+    });
+    assert_eq!(res.messages, vec![send.clone(), send]);
+
+    // removed guardian cannot pause anymore
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::UpdateGuardian { guardian: None },
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::UpdateGuardian { guardian: None },
+    )
+    .unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("guardian", &[]),
+        ExecuteMsg::SetPaused {
+            claims: Some(true),
+            registrations: None,
+            deregistrations: None,
+        },
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    assert_eq!(
+        from_binary::<ConfigResponse>(&query(deps.as_ref(), env, QueryMsg::Config {}).unwrap())
+            .unwrap(),
+        ConfigResponse {
+            require_transfer_approval: false,
+            guardian: None,
+            paused: PausedOperations {
+                claims: false,
+                registrations: true,
+                deregistrations: true,
+            },
+            unbonding_period: 1814400,
+        }
+    );
+}
+
+#[test]
+fn query_vesting_projection() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::HybridVesting {
+            cliff_time: Uint64::new(100),
+            cliff_amount: Uint128::new(200000u128),
+            end_time: Uint64::new(140),
+            vesting_interval: Some(Uint64::new(10)),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    let vested_amount_at = |deps: Deps, time: u64| -> VestedAmountAtResponse {
+        from_binary(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::VestedAmountAt {
+                    address: "addr0001".to_string(),
+                    grant_id: 1,
+                    time,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let timeline = |deps: Deps, start_after: Option<u64>, limit: Option<u32>| {
+        from_binary::<VestingTimelineResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::VestingTimeline {
+                    address: "addr0001".to_string(),
+                    grant_id: 1,
+                    start_after,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .breakpoints
+        .into_iter()
+        .map(|b| (b.time, b.vested_amount.u128()))
+        .collect::<Vec<(u64, u128)>>()
+    };
+
+    assert_eq!(
+        vested_amount_at(deps.as_ref(), 125),
+        VestedAmountAtResponse {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+            time: 125,
+            time_unit: TimeUnit::Seconds,
+            vested_amount: Uint128::new(600000u128),
+            claimable_amount: Uint128::new(600000u128),
+        }
+    );
+    assert_eq!(
+        timeline(deps.as_ref(), None, None),
+        vec![
+            (100, 200000),
+            (110, 400000),
+            (120, 600000),
+            (130, 800000),
+            (140, 1000000),
+        ]
+    );
+    assert_eq!(
+        timeline(deps.as_ref(), Some(110), Some(2)),
+        vec![(120, 600000), (130, 800000)]
+    );
+
+    // claimed amount is deducted from the projected claimable amount
+    env.block.time = Timestamp::from_seconds(112);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        ExecuteMsg::ClaimAll { recipient: None },
+    )
+    .unwrap();
+    assert_eq!(
+        vested_amount_at(deps.as_ref(), 135).claimable_amount,
+        Uint128::new(400000u128)
+    );
+
+    // a pause of 5 seconds postpones the rest of the timeline
+    let pause = ExecuteMsg::PauseVesting {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+    };
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        pause,
+    )
+    .unwrap();
+
+    // nothing more is projected while the pause is ongoing
+    assert_eq!(
+        vested_amount_at(deps.as_ref(), 200).vested_amount,
+        Uint128::new(400000u128)
+    );
+    assert_eq!(
+        timeline(deps.as_ref(), Some(100), None),
+        vec![(110, 400000), (112, 400000)]
+    );
+
+    env.block.time = Timestamp::from_seconds(117);
+    let resume = ExecuteMsg::ResumeVesting {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+    };
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        resume,
+    )
+    .unwrap();
+    assert_eq!(
+        vested_amount_at(deps.as_ref(), 125).vested_amount,
+        Uint128::new(600000u128)
+    );
+    assert_eq!(
+        timeline(deps.as_ref(), Some(100), None),
+        vec![
+            (110, 400000),
+            (112, 400000),
+            (117, 400000),
+            (125, 600000),
+            (135, 800000),
+            (145, 1000000),
+        ]
+    );
+
+    // the timeline stops at the revocation
+    env.block.time = Timestamp::from_seconds(130);
+    let revoke = ExecuteMsg::RevokeVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        left_vesting_token_recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env, mock_info("addr0000", &[]), revoke).unwrap();
+    assert_eq!(
+        vested_amount_at(deps.as_ref(), 145).vested_amount,
+        Uint128::new(600000u128)
+    );
+    assert_eq!(
+        timeline(deps.as_ref(), Some(117), None),
+        vec![(125, 600000), (130, 600000)]
+    );
+
+    let res = query(
+        deps.as_ref(),
+        mock_env(),
+        QueryMsg::VestedAmountAt {
+            address: "addr0001".to_string(),
+            grant_id: 2,
+            time: 100,
+        },
+    );
+    match res.unwrap_err() {
+        StdError::NotFound { .. } => {}
+        _ => panic!("should not enter"),
+    }
+}
+
+#[test]
+fn query_claim_history_and_archived_accounts() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(110),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: vesting_schedule.clone(),
+        time_unit: None,
+    };
+    for _ in 0..2 {
+        let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
+    }
+
+    let claim = |grant_id: u64, recipient: Option<String>| ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: Some(vec![grant_id]),
+        recipient,
+    };
+
+    env.block.time = Timestamp::from_seconds(103);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim(1, None),
+    )
+    .unwrap();
+
+    env.block.time = Timestamp::from_seconds(104);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        ExecuteMsg::DeregisterVestingAccount {
+            address: "addr0001".to_string(),
+            grant_id: 2,
+            vested_token_recipient: None,
+            left_vesting_token_recipient: None,
+        },
+    )
+    .unwrap();
+
+    env.block.time = Timestamp::from_seconds(106);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim(1, Some("addr0002".to_string())),
+    )
+    .unwrap();
+
+    env.block.time = Timestamp::from_seconds(110);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim(1, None),
+    )
+    .unwrap();
+
+    let claim_history = |deps: Deps, start_after: Option<u64>, limit: Option<u32>| {
+        from_binary::<ClaimHistoryResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::ClaimHistory {
+                    grant_id: 1,
+                    start_after,
+                    limit,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    let record = |claim_id: u64, timestamp: u64, amount: u128, recipient: &str| ClaimRecordData {
+        claim_id,
+        timestamp,
+        height: mock_env().block.height,
+        amount: Uint128::new(amount),
+        recipient: recipient.to_string(),
+        kind: ClaimKind::Vested,
+    };
+
+    assert_eq!(
+        claim_history(deps.as_ref(), None, None),
+        ClaimHistoryResponse {
+            grant_id: 1,
+            claims: vec![
+                record(1, 103, 300000, "addr0001"),
+                record(3, 106, 300000, "addr0002"),
+                record(4, 110, 400000, "addr0001"),
+            ],
+        }
+    );
+    assert_eq!(
+        claim_history(deps.as_ref(), Some(1), Some(1)).claims,
+        vec![record(3, 106, 300000, "addr0002")]
+    );
+
+    // deregistration pays the vested amount as a claim
+    assert_eq!(
+        from_binary::<ClaimHistoryResponse>(
+            &query(
+                deps.as_ref(),
+                mock_env(),
+                QueryMsg::ClaimHistory {
+                    grant_id: 2,
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap()
+        .claims,
+        vec![record(2, 104, 400000, "addr0001")]
+    );
+
+    // both grants are removed and archived
+    assert_eq!(
+        from_binary::<VestingAccountResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::VestingAccount {
+                    address: "addr0001".to_string(),
+                    start_after: None,
+                    limit: None,
+                },
+            )
+            .unwrap()
+        )
+        .unwrap()
+        .vestings,
+        vec![]
+    );
+    let archived = |deps: Deps, start_after: Option<u64>| {
+        from_binary::<ArchivedVestingAccountsResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::ArchivedVestingAccounts {
+                    address: "addr0001".to_string(),
+                    start_after,
+                    limit: None,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+    };
+    assert_eq!(
+        archived(deps.as_ref(), None),
+        ArchivedVestingAccountsResponse {
+            address: "addr0001".to_string(),
+            vestings: vec![
+                ArchivedVestingData {
+                    grant_id: 1,
+                    vesting_denom: Denom::Native("uusd".to_string()),
+                    vesting_amount: Uint128::new(1000000u128),
+                    claimed_amount: Uint128::new(1000000u128),
+                    vesting_schedule: vesting_schedule.clone(),
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                    reason: ArchiveReason::Completed,
+                    archived_at: 110,
+                },
+                ArchivedVestingData {
+                    grant_id: 2,
+                    vesting_denom: Denom::Native("uusd".to_string()),
+                    vesting_amount: Uint128::new(1000000u128),
+                    claimed_amount: Uint128::new(400000u128),
+                    vesting_schedule,
+                    time_unit: TimeUnit::Seconds,
+                    revoked_at: None,
+                    paused_periods: vec![],
+                    reason: ArchiveReason::Deregistered,
+                    archived_at: 104,
+                },
+            ],
+        }
+    );
+    assert_eq!(
+        archived(deps.as_ref(), Some(1))
+            .vestings
+            .into_iter()
+            .map(|v| v.grant_id)
+            .collect::<Vec<u64>>(),
+        vec![2]
+    );
+}
+
+#[test]
+fn stake_unvested_tokens() {
+    let mut deps = mock_querier::mock_dependencies(&[]);
+    deps.querier.update_staking("uluna", &[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let vesting_schedule = VestingSchedule::LinearVesting {
+        start_time: Uint64::new(100),
+        end_time: Uint64::new(200),
+        vesting_amount: Uint128::new(1000000u128),
+    };
+    for denom in ["uluna", "uusd"] {
+        let msg = ExecuteMsg::RegisterVestingAccount {
+            address: "addr0001".to_string(),
+            vesting_schedule: vesting_schedule.clone(),
+            time_unit: None,
+        };
+        let info = mock_info("addr0000", &[Coin::new(1000000u128, denom)]);
+        let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    }
+
+    let delegation = |validator: &str, amount: u128, rewards: u128| FullDelegation {
+        delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+        validator: validator.to_string(),
+        amount: Coin::new(amount, "uluna"),
+        can_redelegate: Coin::new(amount, "uluna"),
+        accumulated_rewards: vec![Coin::new(rewards, "uluna"), Coin::new(rewards, "uusd")],
+    };
+    let withdraw = |validator: &str| -> CosmosMsg {
+        DistributionMsg::WithdrawDelegatorReward {
+            validator: validator.to_string(),
+        }
+        .into()
+    };
+
+    let msg = ExecuteMsg::Delegate {
+        grant_id: 2,
+        validator: "val1".to_string(),
+        amount: Uint128::new(100u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::InvalidStakingDenom { denom } => assert_eq!(denom, "{\"native\":\"uusd\"}"),
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::Delegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(600000u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(StakingMsg::Delegate {
+            validator: "val1".to_string(),
+            amount: Coin::new(600000u128, "uluna"),
+        })]
+    );
+    deps.querier
+        .update_staking("uluna", &[delegation("val1", 600000, 6000)]);
+
+    // the delegated tokens are owed while they are away from the balance
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![
+            Coin::new(400050u128, "uluna"),
+            Coin::new(1000000u128, "uusd"),
+        ],
+    );
+    let msg = ExecuteMsg::SweepSurplus {
+        denom: Denom::Native("uluna".to_string()),
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0000".to_string(),
+            amount: vec![Coin::new(50u128, "uluna")],
+        })]
+    );
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![
+            Coin::new(400000u128, "uluna"),
+            Coin::new(1000000u128, "uusd"),
+        ],
+    );
+
+    // only the unvested tokens which are not delegated yet
+    env.block.time = Timestamp::from_seconds(150);
+    let msg = ExecuteMsg::Delegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(500000u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::ExceedsUnvestedAmount { amount, available } => {
+            assert_eq!(amount, Uint128::new(500000u128));
+            assert_eq!(available, Uint128::zero());
+        }
+        _ => panic!("should not enter"),
+    }
+
+    // the delegated tokens are not claimable until they are back to the contract
+    let claim_msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uluna".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(400000u128, "uluna")],
+        })]
+    );
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000000u128, "uusd")]);
+
+    let msg = ExecuteMsg::Redelegate {
+        grant_id: 1,
+        src_validator: "val1".to_string(),
+        dst_validator: "val2".to_string(),
+        amount: Uint128::new(200000u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(withdraw("val1")),
+            SubMsg::new(StakingMsg::Redelegate {
+                src_validator: "val1".to_string(),
+                dst_validator: "val2".to_string(),
+                amount: Coin::new(200000u128, "uluna"),
+            }),
+        ]
+    );
+    deps.querier.update_staking(
+        "uluna",
+        &[delegation("val1", 400000, 0), delegation("val2", 200000, 0)],
+    );
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![Coin::new(6000u128, "uluna"), Coin::new(1000000u128, "uusd")],
+    );
+
+    let msg = ExecuteMsg::Undelegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(500000u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::ExceedsDelegation {
+            validator,
+            delegated,
+            ..
+        } => {
+            assert_eq!(validator, "val1");
+            assert_eq!(delegated, Uint128::new(400000u128));
+        }
+        _ => panic!("should not enter"),
+    }
+
+    // an unbonding period beyond the representable time is rejected
+    let update_unbonding_period = |unbonding_period: u64| ExecuteMsg::UpdateConfig {
+        require_transfer_approval: None,
+        unbonding_period: Some(unbonding_period),
+    };
+    let info = mock_info("addr0000", &[]);
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        info.clone(),
+        update_unbonding_period(u64::MAX),
+    )
+    .unwrap();
+    let msg = ExecuteMsg::Undelegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(400000u128),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Overflow(OverflowError { operation, .. }) => {
+            assert_eq!(operation, OverflowOperation::Add)
+        }
+        _ => panic!("should not enter"),
+    }
+    let _ = execute(
+        deps.as_mut(),
+        env.clone(),
+        info,
+        update_unbonding_period(21 * 24 * 60 * 60),
+    )
+    .unwrap();
+
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(withdraw("val1")),
+            SubMsg::new(StakingMsg::Undelegate {
+                validator: "val1".to_string(),
+                amount: Coin::new(400000u128, "uluna"),
+            }),
+        ]
+    );
+    deps.querier
+        .update_staking("uluna", &[delegation("val2", 200000, 1000)]);
+
+    // the unbonding tokens are owed until they are back in the balance
+    assert_eq!(
+        from_binary::<LockedBalanceResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::LockedBalance {
+                    denom: Denom::Native("uluna".to_string()),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        LockedBalanceResponse {
+            vesting_denom: Denom::Native("uluna".to_string()),
+            deposited_amount: Uint128::new(1006000u128),
+            claimed_amount: Uint128::new(400000u128),
+            locked_amount: Uint128::new(606000u128),
+            delegated_amount: Uint128::new(200000u128),
+            unbonding_amount: Uint128::new(400000u128),
+            balance: Uint128::new(6000u128),
+        }
+    );
+    let msg = ExecuteMsg::SweepSurplus {
+        denom: Denom::Native("uluna".to_string()),
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::NoSurplus { .. } => {}
+        _ => panic!("should not enter"),
+    }
+
+    let completion_time = 150 + 21 * 24 * 60 * 60;
+    assert_eq!(
+        from_binary::<DelegationsResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::Delegations {
+                    address: "addr0001".to_string(),
+                    grant_id: 1,
                 },
-            ],
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        DelegationsResponse {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+            delegations: vec![DelegationData {
+                validator: "val2".to_string(),
+                amount: Uint128::new(200000u128),
+            }],
+            unbondings: vec![Unbonding {
+                amount: Uint128::new(400000u128),
+                completion_time,
+                position: Uint128::new(400000u128),
+            }],
+            rewards: Uint128::new(7000u128),
+            slashed_amount: Uint128::zero(),
+        }
+    );
+
+    let deregister_msg = ExecuteMsg::DeregisterVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        vested_token_recipient: None,
+        left_vesting_token_recipient: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        deregister_msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::VestingStaked { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+
+    // the rewards in the bonded denom are sent after they are withdrawn
+    let msg = ExecuteMsg::WithdrawRewards { grant_id: 1 };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(withdraw("val2")),
+            SubMsg::new(BankMsg::Send {
+                to_address: "addr0001".to_string(),
+                amount: vec![Coin::new(7000u128, "uluna")],
+            }),
+        ]
+    );
+
+    // and recorded in the claim history apart from the vested amount
+    let res: ClaimHistoryResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::ClaimHistory {
+                grant_id: 1,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    let record = res.claims.last().unwrap();
+    assert_eq!(record.amount, Uint128::new(7000u128));
+    assert_eq!(record.kind, ClaimKind::Rewards);
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000000u128, "uusd")]);
+    deps.querier
+        .update_staking("uluna", &[delegation("val2", 200000, 0)]);
+
+    env.block.time = Timestamp::from_seconds(151);
+    let msg = ExecuteMsg::ForceUndelegate {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        msg.clone(),
+    );
+    match res.unwrap_err() {
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![
+            SubMsg::new(withdraw("val2")),
+            SubMsg::new(StakingMsg::Undelegate {
+                validator: "val2".to_string(),
+                amount: Coin::new(200000u128, "uluna"),
+            }),
+        ]
+    );
+    deps.querier.update_staking("uluna", &[]);
+
+    // the unbonding is not claimable until the tokens show up in the balance
+    env.block.time = Timestamp::from_seconds(completion_time);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(res.messages, vec![]);
+
+    // the first unbonding is back to the contract
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![
+            Coin::new(400000u128, "uluna"),
+            Coin::new(1000000u128, "uusd"),
+        ],
+    );
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        deregister_msg,
+    );
+    match res.unwrap_err() {
+        ContractError::VestingStaked { grant_id } => assert_eq!(grant_id, 1),
+        _ => panic!("should not enter"),
+    }
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg.clone(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(400000u128, "uluna")],
+        })]
+    );
+    deps.querier.update_balance(
+        MOCK_CONTRACT_ADDR,
+        vec![
+            Coin::new(200000u128, "uluna"),
+            Coin::new(1000000u128, "uusd"),
+        ],
+    );
+
+    env.block.time = Timestamp::from_seconds(completion_time + 1);
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        claim_msg,
+    )
+    .unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(200000u128, "uluna")],
+        })]
+    );
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(1000000u128, "uusd")]);
+
+    // the rewards are accounted in the locked balance
+    assert_eq!(
+        from_binary::<LockedBalanceResponse>(
+            &query(
+                deps.as_ref(),
+                env,
+                QueryMsg::LockedBalance {
+                    denom: Denom::Native("uluna".to_string()),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        LockedBalanceResponse {
+            vesting_denom: Denom::Native("uluna".to_string()),
+            deposited_amount: Uint128::new(1007000u128),
+            claimed_amount: Uint128::new(1007000u128),
+            locked_amount: Uint128::zero(),
+            delegated_amount: Uint128::zero(),
+            unbonding_amount: Uint128::zero(),
+            balance: Uint128::zero(),
+        }
+    );
+}
+
+#[test]
+fn slash_delegated_tokens() {
+    let mut deps = mock_querier::mock_dependencies(&[]);
+    deps.querier.update_staking("uluna", &[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(200),
+            vesting_amount: Uint128::new(1000000u128),
+        },
+        time_unit: None,
+    };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uluna")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+    for (validator, amount) in [("val1", 500000u128), ("val2", 100000u128)] {
+        let msg = ExecuteMsg::Delegate {
+            grant_id: 1,
+            validator: validator.to_string(),
+            amount: Uint128::new(amount),
+        };
+        let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    }
+
+    // val1 is slashed by 10% and val2 loses everything
+    deps.querier.update_staking(
+        "uluna",
+        &[FullDelegation {
+            delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            validator: "val1".to_string(),
+            amount: Coin::new(450000u128, "uluna"),
+            can_redelegate: Coin::new(450000u128, "uluna"),
+            accumulated_rewards: vec![],
+        }],
+    );
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(400000u128, "uluna")]);
+
+    let delegations_msg = QueryMsg::Delegations {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+    };
+    let expected = DelegationsResponse {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        delegations: vec![DelegationData {
+            validator: "val1".to_string(),
+            amount: Uint128::new(450000u128),
+        }],
+        unbondings: vec![],
+        rewards: Uint128::zero(),
+        slashed_amount: Uint128::new(150000u128),
+    };
+    assert_eq!(
+        from_binary::<DelegationsResponse>(
+            &query(deps.as_ref(), env.clone(), delegations_msg.clone()).unwrap()
+        )
+        .unwrap(),
+        expected
+    );
+
+    // the slashing is settled with the grant
+    let msg = ExecuteMsg::WithdrawRewards { grant_id: 1 };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        from_binary::<DelegationsResponse>(
+            &query(deps.as_ref(), env.clone(), delegations_msg).unwrap()
+        )
+        .unwrap(),
+        expected
+    );
+    assert_eq!(
+        from_binary::<LockedBalanceResponse>(
+            &query(
+                deps.as_ref(),
+                env.clone(),
+                QueryMsg::LockedBalance {
+                    denom: Denom::Native("uluna".to_string()),
+                },
+            )
+            .unwrap()
+        )
+        .unwrap(),
+        LockedBalanceResponse {
+            vesting_denom: Denom::Native("uluna".to_string()),
+            deposited_amount: Uint128::new(1000000u128),
+            claimed_amount: Uint128::zero(),
+            locked_amount: Uint128::new(850000u128),
+            delegated_amount: Uint128::new(450000u128),
+            unbonding_amount: Uint128::zero(),
+            balance: Uint128::new(400000u128),
+        }
+    );
+
+    let res: VotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::VotingPower {
+                address: "addr0001".to_string(),
+                denom: Some(Denom::Native("uluna".to_string())),
+                height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.voting_powers,
+        vec![DenomVotingPower {
+            denom: Denom::Native("uluna".to_string()),
+            voting_power: Uint128::new(850000u128),
+        }]
+    );
+
+    // the slashed tokens can not be delegated again
+    let msg = ExecuteMsg::Delegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(400001u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::ExceedsUnvestedAmount { available, .. } => {
+            assert_eq!(available, Uint128::new(400000u128))
+        }
+        _ => panic!("should not enter"),
+    }
+    let msg = ExecuteMsg::Delegate {
+        grant_id: 1,
+        validator: "val2".to_string(),
+        amount: Uint128::new(100u128),
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg);
+    match res.unwrap_err() {
+        ContractError::ValidatorSlashed { validator } => assert_eq!(validator, "val2"),
+        _ => panic!("should not enter"),
+    }
+
+    let msg = ExecuteMsg::Undelegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(450000u128),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    deps.querier.update_staking("uluna", &[]);
+
+    // the grant is completed with the tokens left after slashing
+    env.block.time = Timestamp::from_seconds(100 + 21 * 24 * 60 * 60);
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(850000u128, "uluna")]);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uluna".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let res = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(850000u128, "uluna")],
+        })]
+    );
+    let res = query(
+        deps.as_ref(),
+        env,
+        QueryMsg::Delegations {
+            address: "addr0001".to_string(),
+            grant_id: 1,
+        },
+    );
+    assert!(res.is_err());
+}
+
+#[test]
+fn write_off_slashed_unbonding() {
+    let mut deps = mock_querier::mock_dependencies(&[]);
+    deps.querier.update_staking("uluna", &[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.time = Timestamp::from_seconds(100);
+
+    let msg = ExecuteMsg::RegisterVestingAccount {
+        address: "addr0001".to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(200),
+            vesting_amount: Uint128::new(1000000u128),
         },
+        time_unit: None,
     };
+    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uluna")]);
+    let _ = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-    // invalid amount
-    let info = mock_info("addr0000", &[]);
-    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    let msg = ExecuteMsg::Delegate {
+        grant_id: 1,
+        validator: "val1".to_string(),
+        amount: Uint128::new(400000u128),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+    deps.querier.update_staking(
+        "uluna",
+        &[FullDelegation {
+            delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+            validator: "val1".to_string(),
+            amount: Coin::new(400000u128, "uluna"),
+            can_redelegate: Coin::new(400000u128, "uluna"),
+            accumulated_rewards: vec![],
+        }],
+    );
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(600000u128, "uluna")]);
+
+    // two unbondings, which are back in the order they were undelegated
+    for amount in [200000u128, 200000u128] {
+        let msg = ExecuteMsg::Undelegate {
+            grant_id: 1,
+            validator: "val1".to_string(),
+            amount: Uint128::new(amount),
+        };
+        let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+        deps.querier.update_staking(
+            "uluna",
+            &[FullDelegation {
+                delegator: Addr::unchecked(MOCK_CONTRACT_ADDR),
+                validator: "val1".to_string(),
+                amount: Coin::new(200000u128, "uluna"),
+                can_redelegate: Coin::new(200000u128, "uluna"),
+                accumulated_rewards: vec![],
+            }],
+        );
+    }
+    deps.querier.update_staking("uluna", &[]);
+
+    let write_off = |amount: u128| ExecuteMsg::WriteOffUnbonding {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        amount: Uint128::new(amount),
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        write_off(40000),
+    );
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => assert_eq!(msg, "must deposit only one type of token"),
+        ContractError::ExceedsOverdueUnbonding { overdue, .. } => {
+            assert_eq!(overdue, Uint128::zero())
+        }
         _ => panic!("should not enter"),
     }
 
-    // invalid amount
-    let info = mock_info(
-        "addr0000",
-        &[Coin::new(100u128, "uusd"), Coin::new(10u128, "ukrw")],
+    // the first unbonding is slashed by 40000 while unbonding, which never comes back
+    env.block.time = env.block.time.plus_seconds(21 * 24 * 60 * 60);
+    deps.querier
+        .update_balance(MOCK_CONTRACT_ADDR, vec![Coin::new(960000u128, "uluna")]);
+
+    let deregister = ExecuteMsg::DeregisterVestingAccount {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+        vested_token_recipient: None,
+        left_vesting_token_recipient: None,
+    };
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        deregister.clone(),
     );
-    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => assert_eq!(msg, "must deposit only one type of token"),
+        ContractError::VestingStaked { grant_id } => assert_eq!(grant_id, 1),
         _ => panic!("should not enter"),
     }
 
-    // invalid amount
-    let info = mock_info("addr0000", &[Coin::new(10u128, "uusd")]);
-    let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+    let delegations_msg = QueryMsg::Delegations {
+        address: "addr0001".to_string(),
+        grant_id: 1,
+    };
+    let res: DelegationsResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), delegations_msg.clone()).unwrap()).unwrap();
+    assert_eq!(
+        res.unbondings,
+        vec![Unbonding {
+            amount: Uint128::new(200000u128),
+            completion_time: 100 + 21 * 24 * 60 * 60,
+            position: Uint128::new(400000u128),
+        }]
+    );
+
+    // only an admin writes off, at most the overdue unbondings
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0001", &[]),
+        write_off(40000),
+    );
     match res.unwrap_err() {
-        StdError::GenericErr { msg, .. } => {
-            assert_eq!(msg, "assert(deposit_amount == vesting_amount)")
+        ContractError::Unauthorized {} => {}
+        _ => panic!("should not enter"),
+    }
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        write_off(300000),
+    );
+    match res.unwrap_err() {
+        ContractError::ExceedsOverdueUnbonding {
+            grant_id,
+            amount,
+            overdue,
+        } => {
+            assert_eq!(grant_id, 1);
+            assert_eq!(amount, Uint128::new(300000u128));
+            assert_eq!(overdue, Uint128::new(200000u128));
         }
         _ => panic!("should not enter"),
     }
 
-    // valid amount
-    let info = mock_info("addr0000", &[Coin::new(1000000u128, "uusd")]);
-    let res: Response = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+    let res = execute(
+        deps.as_mut(),
+        env.clone(),
+        mock_info("addr0000", &[]),
+        write_off(40000),
+    )
+    .unwrap();
     assert_eq!(
         res.attributes,
         vec![
-            ("action", "register_vesting_account"),
+            ("action", "write_off_unbonding"),
             ("address", "addr0001"),
-            ("vesting_denom", "{\"native\":\"uusd\"}"),
-            ("vesting_amount", "1000000"),
+            ("grant_id", "1"),
+            ("amount", "40000"),
         ]
     );
 
-    // query vesting account
+    // the written off amount is taken as returned, so the later unbonding is back
+    let res: DelegationsResponse =
+        from_binary(&query(deps.as_ref(), env.clone(), delegations_msg).unwrap()).unwrap();
+    assert_eq!(res.unbondings, vec![]);
+    assert_eq!(res.slashed_amount, Uint128::new(40000u128));
     assert_eq!(
-        from_binary::<VestingAccountResponse>(
+        from_binary::<LockedBalanceResponse>(
             &query(
                 deps.as_ref(),
-                env,
-                QueryMsg::VestingAccount {
-                    address: "addr0001".to_string(),
-                    start_after: None,
-                    limit: None,
+                env.clone(),
+                QueryMsg::LockedBalance {
+                    denom: Denom::Native("uluna".to_string()),
                 },
             )
             .unwrap()
         )
         .unwrap(),
-        VestingAccountResponse {
+        LockedBalanceResponse {
+            vesting_denom: Denom::Native("uluna".to_string()),
+            deposited_amount: Uint128::new(1000000u128),
+            claimed_amount: Uint128::zero(),
+            locked_amount: Uint128::new(960000u128),
+            delegated_amount: Uint128::zero(),
+            unbonding_amount: Uint128::zero(),
+            balance: Uint128::new(960000u128),
+        }
+    );
+    let res: VotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::VotingPower {
+                address: "addr0001".to_string(),
+                denom: Some(Denom::Native("uluna".to_string())),
+                height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.voting_powers[0].voting_power, Uint128::new(960000u128));
+
+    let res = execute(deps.as_mut(), env, mock_info("addr0000", &[]), deregister).unwrap();
+    assert_eq!(
+        res.messages,
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: "addr0001".to_string(),
+            amount: vec![Coin::new(960000u128, "uluna")],
+        })]
+    );
+}
+
+#[test]
+fn query_voting_power() {
+    let mut deps = mock_dependencies(&[]);
+    let _res = instantiate(
+        deps.as_mut(),
+        mock_env(),
+        mock_info("addr0000", &[]),
+        InstantiateMsg {
+            master_address: None,
+            require_transfer_approval: None,
+            guardian: None,
+        },
+    )
+    .unwrap();
+
+    let mut env = mock_env();
+    env.block.height = 100;
+    env.block.time = Timestamp::from_seconds(100);
+
+    let register = |address: &str, amount: u128| ExecuteMsg::RegisterVestingAccount {
+        address: address.to_string(),
+        vesting_schedule: VestingSchedule::LinearVesting {
+            start_time: Uint64::new(100),
+            end_time: Uint64::new(200),
+            vesting_amount: Uint128::new(amount),
+        },
+        time_unit: None,
+    };
+    for (address, amount, denom) in [
+        ("addr0001", 1000000u128, "uusd"),
+        ("addr0001", 200000u128, "ukrw"),
+        ("addr0002", 300000u128, "uusd"),
+    ] {
+        let info = mock_info("addr0000", &[Coin::new(amount, denom)]);
+        let _ = execute(deps.as_mut(), env.clone(), info, register(address, amount)).unwrap();
+    }
+
+    // the claimed amount is not counted
+    env.block.height = 101;
+    env.block.time = Timestamp::from_seconds(150);
+    let msg = ExecuteMsg::Claim {
+        denoms: vec![Denom::Native("uusd".to_string())],
+        grant_ids: None,
+        recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    // the voting power moves with the grant
+    env.block.height = 102;
+    let msg = ExecuteMsg::TransferVestingAccount {
+        grant_id: 1,
+        new_address: "addr0002".to_string(),
+    };
+    let _ = execute(deps.as_mut(), env.clone(), mock_info("addr0001", &[]), msg).unwrap();
+
+    let voting_power = |deps: Deps, address: &str, denom: &str, height: Option<u64>| {
+        from_binary::<VotingPowerResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::VotingPower {
+                    address: address.to_string(),
+                    denom: Some(Denom::Native(denom.to_string())),
+                    height,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .voting_powers[0]
+            .voting_power
+    };
+    let total_voting_power = |deps: Deps, denom: &str, height: Option<u64>| {
+        from_binary::<TotalVotingPowerResponse>(
+            &query(
+                deps,
+                mock_env(),
+                QueryMsg::TotalVotingPower {
+                    denom: Some(Denom::Native(denom.to_string())),
+                    height,
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap()
+        .voting_powers[0]
+            .voting_power
+    };
+
+    // snapshots are taken at the start of the block height
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", "uusd", Some(100)),
+        Uint128::zero()
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", "uusd", Some(101)),
+        Uint128::new(1000000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", "uusd", Some(102)),
+        Uint128::new(500000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", "uusd", None),
+        Uint128::zero()
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", "ukrw", Some(101)),
+        Uint128::new(200000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0001", "ukrw", None),
+        Uint128::new(200000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0002", "uusd", Some(102)),
+        Uint128::new(300000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0002", "uusd", None),
+        Uint128::new(800000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0003", "uusd", None),
+        Uint128::zero()
+    );
+
+    assert_eq!(
+        total_voting_power(deps.as_ref(), "uusd", Some(101)),
+        Uint128::new(1300000u128)
+    );
+    assert_eq!(
+        total_voting_power(deps.as_ref(), "uusd", None),
+        Uint128::new(800000u128)
+    );
+    assert_eq!(
+        total_voting_power(deps.as_ref(), "ukrw", None),
+        Uint128::new(200000u128)
+    );
+
+    // without a denom, the voting power is given per denom rather than summed
+    let res: VotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::VotingPower {
+                address: "addr0001".to_string(),
+                denom: None,
+                height: Some(101),
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        VotingPowerResponse {
             address: "addr0001".to_string(),
-            vestings: vec![VestingData {
-                vesting_denom: Denom::Native("uusd".to_string()),
-                vesting_amount: Uint128::new(1000000),
-                vested_amount: Uint128::zero(),
-                vesting_schedule: VestingSchedule::CliffVesting {
-                    schedules: vec![
-                        CliffSchedule {
-                            release_time: "105".to_string(),
-                            release_amount: Uint128::new(500000u128),
-                        },
-                        CliffSchedule {
-                            release_time: "110".to_string(),
-                            release_amount: Uint128::new(500000u128),
-                        },
-                    ],
+            height: Some(101),
+            voting_powers: vec![
+                DenomVotingPower {
+                    denom: Denom::Native("ukrw".to_string()),
+                    voting_power: Uint128::new(200000u128),
                 },
-                claimable_amount: Uint128::zero(),
-            }],
+                DenomVotingPower {
+                    denom: Denom::Native("uusd".to_string()),
+                    voting_power: Uint128::new(1000000u128),
+                },
+            ],
         }
     );
+
+    // a denom no longer held is listed at zero
+    let res: VotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::VotingPower {
+                address: "addr0001".to_string(),
+                denom: None,
+                height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res.voting_powers,
+        vec![
+            DenomVotingPower {
+                denom: Denom::Native("ukrw".to_string()),
+                voting_power: Uint128::new(200000u128),
+            },
+            DenomVotingPower {
+                denom: Denom::Native("uusd".to_string()),
+                voting_power: Uint128::zero(),
+            },
+        ]
+    );
+
+    let res: TotalVotingPowerResponse = from_binary(
+        &query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::TotalVotingPower {
+                denom: None,
+                height: None,
+            },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(
+        res,
+        TotalVotingPowerResponse {
+            height: None,
+            voting_powers: vec![
+                DenomVotingPower {
+                    denom: Denom::Native("ukrw".to_string()),
+                    voting_power: Uint128::new(200000u128),
+                },
+                DenomVotingPower {
+                    denom: Denom::Native("uusd".to_string()),
+                    voting_power: Uint128::new(800000u128),
+                },
+            ],
+        }
+    );
+
+    // the returned amount is not counted either
+    env.block.height = 103;
+    let msg = ExecuteMsg::DeregisterVestingAccount {
+        address: "addr0002".to_string(),
+        grant_id: 3,
+        vested_token_recipient: None,
+        left_vesting_token_recipient: None,
+    };
+    let _ = execute(deps.as_mut(), env, mock_info("addr0000", &[]), msg).unwrap();
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0002", "uusd", Some(103)),
+        Uint128::new(800000u128)
+    );
+    assert_eq!(
+        voting_power(deps.as_ref(), "addr0002", "uusd", None),
+        Uint128::new(500000u128)
+    );
+    assert_eq!(
+        total_voting_power(deps.as_ref(), "uusd", None),
+        Uint128::new(500000u128)
+    );
 }